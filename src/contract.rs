@@ -1,15 +1,32 @@
 use crate::error::MarketplaceError;
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use crate::events;
+use crate::helpers::{bid_commitment, delivery_code};
+use crate::msg::{
+    ConfigResponse, DeliveryStatusResponse, ExecuteMsg, InstantiateMsg, LocationPreferenceResponse,
+    ModeratorsResponse, OfferResponse, OffersResponse, PauseStatusResponse, PaymentHistoryResponse,
+    PaymentQuoteResponse, QueryMsg, RequestResponse, RequestsResponse, SellerStakeResponse,
+    StoresResponse, UserResponse,
+};
+use crate::oracle;
 use crate::state::{
-    AccountType, CoinPayment, Location, Offer, PaymentInfo, Request, RequestLifecycle, Store, User,
-    COIN_DENOM, OFFERS, OFFER_COUNT, PAYMENT_INFO, REQUESTS, REQUEST_COUNT, STORES, STORE_COUNT,
-    TIME_TO_LOCK, USDT_ADDR, USERS, USERS_BY_ID, USER_COUNT, USER_STORE_IDS,
+    AcceptStrategy, AccountType, AutoAccept, Budget, CoinPayment, Config, DeliveryMilestone,
+    DeliveryStage, LineItem, LineQuote, Location, Offer, OfferRevision, PaymentInfo,
+    PaymentSchedule, Request, RequestLifecycle, RevisionStatus, SealedAuction, Store, User,
+    AUTO_RELEASE_AFTER, COIN_DENOM, CONFIG, MAX_TIME_TO_LOCK, MIN_TIME_TO_LOCK, MODERATORS, OFFERS,
+    OFFER_COUNT, OPEN_ESCROWS, OPEN_REQUESTS, PAUSED, PAYMENT_INFO, REQUESTS, REQUEST_COUNT,
+    SELLER_SALES, SELLER_STAKES, STORES, STORE_COUNT, SUSPENDED_USERS, TIME_TO_LOCK,
+    UNBONDING_PERIOD, USDT_ADDR, USERS, USERS_BY_ID, USER_COUNT, USER_STORE_IDS,
+};
+use crate::validation::{
+    validate_images, validate_line_items, validate_line_quotes, validate_payment_schedule,
+    validate_phone, validate_price, validate_text, MAX_DESCRIPTION_LEN, MAX_NAME_LEN,
+    MAX_TRACKING_REF_LEN, MAX_USERNAME_LEN,
 };
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_json_binary, Binary, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
-    Uint128, WasmMsg,
+    to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Empty, Env, Event,
+    HexBinary, MessageInfo, Response, StdError, StdResult, Storage, Uint128, WasmMsg,
 };
 use cw2::set_contract_version;
 use cw20::Cw20ExecuteMsg;
@@ -22,15 +39,42 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
-    _info: MessageInfo,
-    _msg: InstantiateMsg,
+    info: MessageInfo,
+    msg: InstantiateMsg,
 ) -> Result<Response, MarketplaceError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    let usdt_addr = match msg.usdt_addr {
+        Some(usdt_addr) => deps.api.addr_validate(&usdt_addr)?,
+        None => Addr::unchecked(USDT_ADDR),
+    };
+    let oracle = msg
+        .oracle
+        .map(|oracle| deps.api.addr_validate(&oracle))
+        .transpose()?;
+    let config = Config {
+        admin: info.sender,
+        usdt_addr,
+        time_to_lock: msg.time_to_lock.unwrap_or(TIME_TO_LOCK),
+        min_time_to_lock: msg.min_time_to_lock.unwrap_or(MIN_TIME_TO_LOCK),
+        max_time_to_lock: msg.max_time_to_lock.unwrap_or(MAX_TIME_TO_LOCK),
+        oracle,
+        min_stake: msg.min_stake.unwrap_or_default(),
+        unbonding_period: msg.unbonding_period.unwrap_or(UNBONDING_PERIOD),
+        max_open_requests: msg.max_open_requests.unwrap_or_default(),
+        max_offers_per_request: msg.max_offers_per_request.unwrap_or_default(),
+        max_sellers_per_request: msg.max_sellers_per_request.unwrap_or_default(),
+        creation_fee: msg.creation_fee.unwrap_or_default(),
+        auto_release_after: msg.auto_release_after.unwrap_or(AUTO_RELEASE_AFTER),
+    };
+    validate_config(&config)?;
+    CONFIG.save(deps.storage, &config)?;
     USER_COUNT.save(deps.storage, &1)?;
     STORE_COUNT.save(deps.storage, &1)?;
     REQUEST_COUNT.save(deps.storage, &1)?;
     OFFER_COUNT.save(deps.storage, &1)?;
-    Ok(Response::new().add_attribute("method", "instantiate"))
+    Ok(Response::new()
+        .add_attribute("method", "instantiate")
+        .add_event(events::config_updated(&config)))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -40,6 +84,8 @@ pub fn execute(
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, MarketplaceError> {
+    ensure_not_paused(deps.as_ref(), &msg)?;
+
     match msg {
         ExecuteMsg::CreateUser {
             username,
@@ -95,6 +141,12 @@ pub fn execute(
             images,
             latitude,
             longitude,
+            expires_at,
+            time_to_lock,
+            line_items,
+            budget,
+            auction,
+            auto_accept,
         } => create_request(
             deps,
             info,
@@ -104,22 +156,166 @@ pub fn execute(
             images,
             latitude,
             longitude,
+            expires_at,
+            time_to_lock,
+            line_items,
+            budget,
+            auction,
+            auto_accept,
         ),
         ExecuteMsg::CreateOffer {
             price,
             images,
             request_id,
             store_name,
-        } => create_offer(deps, info, _env, price, images, request_id, store_name),
-        ExecuteMsg::AcceptOffer { offer_id } => accept_offer(deps, info, _env, offer_id),
+            expires_at,
+            payment_schedule,
+            line_quotes,
+        } => create_offer(
+            deps,
+            info,
+            _env,
+            price,
+            images,
+            request_id,
+            store_name,
+            expires_at,
+            payment_schedule,
+            line_quotes,
+        ),
+        ExecuteMsg::UpdateOffer {
+            offer_id,
+            price,
+            images,
+            store_name,
+            expires_at,
+            payment_schedule,
+            line_quotes,
+        } => update_offer(
+            deps,
+            info,
+            _env,
+            offer_id,
+            price,
+            images,
+            store_name,
+            expires_at,
+            payment_schedule,
+            line_quotes,
+        ),
+        ExecuteMsg::CommitOffer {
+            request_id,
+            commitment,
+            images,
+            store_name,
+        } => commit_offer(deps, info, _env, request_id, commitment, images, store_name),
+        ExecuteMsg::RevealOffer {
+            offer_id,
+            price,
+            salt,
+        } => reveal_offer(deps, info, _env, offer_id, price, salt),
+        ExecuteMsg::SettleAuction { request_id } => settle_auction(deps, _env, request_id),
+        ExecuteMsg::FinalizeRequest { request_id } => finalize_request(deps, _env, request_id),
+        ExecuteMsg::CounterOffer { offer_id, price } => {
+            counter_offer(deps, info, _env, offer_id, price)
+        }
+        ExecuteMsg::RespondToCounterOffer { offer_id, accept } => {
+            respond_to_counter_offer(deps, info, _env, offer_id, accept)
+        }
+        ExecuteMsg::AcceptOffer { offer_id, lines } => {
+            accept_offer(deps, info, _env, offer_id, lines)
+        }
         ExecuteMsg::ToggleLocation { enabled } => toggle_location(deps, info, _env, enabled),
         ExecuteMsg::DeleteRequest { request_id } => delete_request(deps, info, _env, request_id),
+        ExecuteMsg::CancelRequest { request_id } => cancel_request(deps, info, _env, request_id),
         ExecuteMsg::MarkRequestAsCompleted { request_id } => {
             mark_request_as_completed(deps, info, _env, request_id)
         }
+        ExecuteMsg::PayForRequest {
+            request_id,
+            coin,
+            max_amount,
+            delivery_code,
+        } => match coin {
+            CoinPayment::USDT => pay_for_request_token(
+                deps,
+                _env,
+                info,
+                request_id,
+                coin,
+                max_amount,
+                delivery_code,
+            ),
+            _ => pay_for_request(
+                deps,
+                _env,
+                info,
+                request_id,
+                coin,
+                max_amount,
+                delivery_code,
+            ),
+        },
+        ExecuteMsg::ForfeitDeposit { request_id } => forfeit_deposit(deps, info, _env, request_id),
+        ExecuteMsg::PostDeliveryMilestone {
+            request_id,
+            stage,
+            tracking_ref,
+        } => post_delivery_milestone(deps, info, _env, request_id, stage, tracking_ref),
+        ExecuteMsg::ConfirmDelivery { request_id, secret } => {
+            confirm_delivery(deps, info, _env, request_id, secret)
+        }
+        ExecuteMsg::ReleaseEscrow { request_id } => release_escrow(deps, _env, request_id),
+        ExecuteMsg::BondStake {} => bond_stake(deps, info, _env),
+        ExecuteMsg::UnbondStake { amount } => unbond_stake(deps, info, _env, amount),
+        ExecuteMsg::WithdrawStake {} => withdraw_stake(deps, info, _env),
+        ExecuteMsg::SlashStake { request_id } => slash_stake(deps, info, _env, request_id),
+        ExecuteMsg::SetPause {
+            all,
+            payments,
+            listings,
+        } => set_pause(deps, info, all, payments, listings),
+        ExecuteMsg::AddModerator { address } => set_moderator(deps, info, address, true),
+        ExecuteMsg::RemoveModerator { address } => set_moderator(deps, info, address, false),
+        ExecuteMsg::SuspendUser { address } => set_suspended(deps, info, _env, address, true),
+        ExecuteMsg::UnsuspendUser { address } => set_suspended(deps, info, _env, address, false),
+        ExecuteMsg::SetRequestHidden { request_id, hidden } => {
+            set_request_hidden(deps, info, request_id, hidden)
+        }
+        ExecuteMsg::SetOfferHidden { offer_id, hidden } => {
+            set_offer_hidden(deps, info, offer_id, hidden)
+        }
+        ExecuteMsg::UpdateConfig {
+            time_to_lock,
+            min_time_to_lock,
+            max_time_to_lock,
+            oracle,
+            min_stake,
+            unbonding_period,
+            max_open_requests,
+            max_offers_per_request,
+            max_sellers_per_request,
+            creation_fee,
+            auto_release_after,
+        } => update_config(
+            deps,
+            info,
+            time_to_lock,
+            min_time_to_lock,
+            max_time_to_lock,
+            oracle,
+            min_stake,
+            unbonding_period,
+            max_open_requests,
+            max_offers_per_request,
+            max_sellers_per_request,
+            creation_fee,
+            auto_release_after,
+        ),
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn create_user(
     deps: DepsMut,
     info: MessageInfo,
@@ -130,6 +326,9 @@ pub fn create_user(
     longitude: i128,
     account_type: AccountType,
 ) -> Result<Response, MarketplaceError> {
+    validate_text("username", &username, MAX_USERNAME_LEN)?;
+    validate_phone(&phone)?;
+
     let user_count = USER_COUNT.load(deps.storage)?;
     let user = User {
         id: user_count,
@@ -150,9 +349,12 @@ pub fn create_user(
     USERS_BY_ID.save(deps.storage, user_count, &user)?;
     USER_COUNT.save(deps.storage, &(user_count + 1))?;
 
-    Ok(Response::new().add_attribute("method", "create_user"))
+    Ok(Response::new()
+        .add_attribute("method", "create_user")
+        .add_event(events::user_created(&user)))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn update_user(
     deps: DepsMut,
     info: MessageInfo,
@@ -163,6 +365,9 @@ pub fn update_user(
     longitude: i128,
     account_type: AccountType,
 ) -> Result<Response, MarketplaceError> {
+    validate_text("username", &username, MAX_USERNAME_LEN)?;
+    validate_phone(&phone)?;
+
     let mut user = USERS.load(deps.storage, info.sender.as_bytes())?;
 
     user.username = username;
@@ -176,9 +381,12 @@ pub fn update_user(
 
     USERS.save(deps.storage, info.sender.as_bytes(), &user)?;
 
-    Ok(Response::new().add_attribute("method", "update_user"))
+    Ok(Response::new()
+        .add_attribute("method", "update_user")
+        .add_event(events::user_updated(&user)))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn create_store(
     deps: DepsMut,
     info: MessageInfo,
@@ -195,6 +403,11 @@ pub fn create_store(
     if user.account_type != AccountType::Seller {
         return Err(MarketplaceError::OnlySellersAllowed);
     }
+
+    validate_text("name", &name, MAX_NAME_LEN)?;
+    validate_text("description", &description, MAX_DESCRIPTION_LEN)?;
+    validate_phone(&phone)?;
+
     let store = Store {
         id: store_count, // Logic for unique ID
         name,
@@ -213,8 +426,11 @@ pub fn create_store(
     store_ids.push(store.id);
     USER_STORE_IDS.save(deps.storage, info.sender.as_bytes(), &store_ids)?;
     STORE_COUNT.save(deps.storage, &(store_count + 1))?;
-    Ok(Response::new().add_attribute("method", "create_store"))
+    Ok(Response::new()
+        .add_attribute("method", "create_store")
+        .add_event(events::store_created(&store, &info.sender)))
 }
+#[allow(clippy::too_many_arguments)]
 pub fn create_request(
     deps: DepsMut,
     info: MessageInfo,
@@ -224,13 +440,69 @@ pub fn create_request(
     images: Vec<String>,
     latitude: i128,
     longitude: i128,
+    expires_at: Option<u64>,
+    time_to_lock: Option<u64>,
+    line_items: Vec<LineItem>,
+    budget: Option<Budget>,
+    auction: Option<SealedAuction>,
+    auto_accept: Option<AutoAccept>,
 ) -> Result<Response, MarketplaceError> {
     let request_count = REQUEST_COUNT.load(deps.storage)?;
     let user = USERS.load(deps.storage, info.sender.as_bytes())?;
+    let config = CONFIG.load(deps.storage)?;
 
     if user.account_type != AccountType::Buyer {
         return Err(MarketplaceError::OnlyBuyersAllowed);
     }
+    ensure_not_suspended(deps.as_ref(), user.id)?;
+
+    validate_text("name", &name, MAX_NAME_LEN)?;
+    validate_text("description", &description, MAX_DESCRIPTION_LEN)?;
+    validate_images(&images)?;
+    validate_line_items(&line_items)?;
+    if let Some(budget) = &budget {
+        validate_price(budget.max_price)?;
+    }
+    validate_expiry(&_env, expires_at)?;
+    if let Some(auction) = &auction {
+        validate_auction(&_env, auction, expires_at)?;
+    }
+    if let Some(auto_accept) = &auto_accept {
+        if auction.is_some()
+            || auto_accept.deadline <= _env.block.time.seconds()
+            || expires_at.is_some_and(|expires_at| expires_at <= auto_accept.deadline)
+        {
+            return Err(MarketplaceError::InvalidAutoAccept);
+        }
+    }
+
+    let time_to_lock = time_to_lock.unwrap_or(config.time_to_lock);
+    if time_to_lock < config.min_time_to_lock || time_to_lock > config.max_time_to_lock {
+        return Err(MarketplaceError::InvalidLockWindow);
+    }
+
+    if let Some(stray) = info.funds.iter().find(|c| c.denom != COIN_DENOM) {
+        return Err(MarketplaceError::UnexpectedFunds {
+            denom: stray.denom.clone(),
+        });
+    }
+    let fee_sent: Uint128 = info.funds.iter().map(|c| c.amount).sum();
+    if fee_sent != config.creation_fee {
+        return Err(MarketplaceError::CreationFeeRequired {
+            fee: config.creation_fee,
+        });
+    }
+
+    let open_requests = OPEN_REQUESTS
+        .may_load(deps.storage, user.id)?
+        .unwrap_or_default();
+    if config.max_open_requests > 0 && open_requests >= config.max_open_requests {
+        return Err(MarketplaceError::TooManyOpenRequests {
+            max: config.max_open_requests,
+        });
+    }
+    OPEN_REQUESTS.save(deps.storage, user.id, &(open_requests + 1))?;
+
     let request = Request {
         id: request_count,
         name,
@@ -250,13 +522,25 @@ pub fn create_request(
         updated_at: _env.block.time.seconds(),
         paid: false,
         accepted_offer_id: 0,
+        expires_at,
+        time_to_lock,
+        line_items,
+        budget,
+        auction,
+        auto_accept,
+        creation_fee: config.creation_fee,
+        delivery: vec![],
+        hidden: false,
     };
 
     REQUESTS.save(deps.storage, request.id, &request)?;
     REQUEST_COUNT.save(deps.storage, &(request_count + 1))?;
 
-    Ok(Response::new().add_attribute("method", "create_request"))
+    Ok(Response::new()
+        .add_attribute("method", "create_request")
+        .add_event(events::request_created(&request, &info.sender)))
 }
+#[allow(clippy::too_many_arguments)]
 pub fn create_offer(
     deps: DepsMut,
     info: MessageInfo,
@@ -265,6 +549,9 @@ pub fn create_offer(
     images: Vec<String>,
     request_id: u64,
     store_name: String,
+    expires_at: Option<u64>,
+    payment_schedule: Option<PaymentSchedule>,
+    line_quotes: Vec<LineQuote>,
 ) -> Result<Response, MarketplaceError> {
     let offer_count = OFFER_COUNT.load(deps.storage)?;
     let user = USERS.load(deps.storage, info.sender.as_bytes())?;
@@ -272,14 +559,41 @@ pub fn create_offer(
     if user.account_type != AccountType::Seller {
         return Err(MarketplaceError::OnlySellersAllowed);
     }
+    ensure_not_suspended(deps.as_ref(), user.id)?;
+    ensure_staked(deps.as_ref(), user.id)?;
+
+    validate_price(price)?;
+    if let Some(schedule) = &payment_schedule {
+        validate_payment_schedule(price, schedule)?;
+    }
+    validate_images(&images)?;
+    validate_text("store_name", &store_name, MAX_NAME_LEN)?;
+    validate_expiry(&_env, expires_at)?;
 
     let mut request = REQUESTS.load(deps.storage, request_id)?;
 
-    if request.lifecycle != RequestLifecycle::Pending {
+    if request.lifecycle == RequestLifecycle::Cancelled {
+        return Err(MarketplaceError::RequestCancelled);
+    }
+
+    if request.is_expired(_env.block.time.seconds()) {
+        return Err(MarketplaceError::RequestExpired);
+    }
+
+    if request.auction.is_some() {
+        return Err(MarketplaceError::SealedBidsOnly);
+    }
+
+    ensure_offer_slot(deps.as_ref(), &_env, &request, user.id)?;
+    check_offer_price(deps.as_ref(), &request, price, &line_quotes)?;
+
+    if request.lifecycle == RequestLifecycle::Pending {
         request.lifecycle = RequestLifecycle::AcceptedBySeller;
     }
 
-    request.seller_ids.push(user.id);
+    if !request.seller_ids.contains(&user.id) {
+        request.seller_ids.push(user.id);
+    }
     request.offer_ids.push(offer_count);
 
     REQUESTS.save(deps.storage, request_id, &request)?;
@@ -292,363 +606,2061 @@ pub fn create_offer(
         store_name,
         seller_id: user.id,
         is_accepted: false,
+        is_cancelled: false,
         created_at: _env.block.time.seconds(),
         updated_at: _env.block.time.seconds(),
         authority: info.sender.clone(),
+        expires_at,
+        payment_schedule,
+        line_quotes,
+        accepted_lines: vec![],
+        commitment: None,
+        revisions: vec![],
+        hidden: false,
     };
 
     OFFERS.save(deps.storage, offer.id, &offer)?;
     OFFER_COUNT.save(deps.storage, &(offer_count + 1))?;
     deps.api.debug("Offer saved successfully");
 
-    Ok(Response::new().add_attribute("method", "create_offer"))
+    Ok(Response::new()
+        .add_attribute("method", "create_offer")
+        .add_event(events::offer_created(&offer)))
 }
-pub fn accept_offer(
+
+/// Checks `price` against the quoted lines and the buyer's budget.
+fn check_offer_price(
+    deps: Deps,
+    request: &Request,
+    price: u128,
+    line_quotes: &[LineQuote],
+) -> Result<(), MarketplaceError> {
+    if !line_quotes.is_empty() && validate_line_quotes(&request.line_items, line_quotes)? != price {
+        return Err(MarketplaceError::PriceMismatch);
+    }
+
+    if let Some(budget) = &request.budget {
+        let config = CONFIG.load(deps.storage)?;
+        if !within_budget(deps, &config, budget, price)? {
+            return Err(MarketplaceError::OfferAboveBudget);
+        }
+    }
+    Ok(())
+}
+
+/// Lets a seller revise their open offer rather than stacking a new one.
+#[allow(clippy::too_many_arguments)]
+pub fn update_offer(
     deps: DepsMut,
     info: MessageInfo,
     _env: Env,
     offer_id: u64,
+    price: u128,
+    images: Vec<String>,
+    store_name: String,
+    expires_at: Option<u64>,
+    payment_schedule: Option<PaymentSchedule>,
+    line_quotes: Vec<LineQuote>,
 ) -> Result<Response, MarketplaceError> {
     let mut offer = OFFERS.load(deps.storage, offer_id)?;
-    let buyer = USERS.load(deps.storage, info.sender.as_bytes())?;
-    let mut request = REQUESTS.load(deps.storage, offer.request_id)?;
 
-    if buyer.account_type != AccountType::Buyer {
-        return Err(MarketplaceError::OnlyBuyersAllowed);
+    if info.sender != offer.authority {
+        return Err(MarketplaceError::UnauthorizedSeller);
     }
+    ensure_not_suspended(deps.as_ref(), offer.seller_id)?;
 
-    if offer.is_accepted {
-        return Err(MarketplaceError::OfferAlreadyAccepted);
+    // A lapsed offer stays dead, otherwise it could be revived next to the
+    // seller's replacement offer.
+    if offer.is_cancelled {
+        return Err(MarketplaceError::OfferCancelled);
+    }
+    if offer.is_expired(_env.block.time.seconds()) {
+        return Err(MarketplaceError::OfferExpired);
     }
 
-    if _env.block.time.seconds() > request.updated_at + TIME_TO_LOCK
-        && request.lifecycle == RequestLifecycle::AcceptedByBuyer
-    {
-        return Err(MarketplaceError::RequestLocked);
+    validate_price(price)?;
+    if let Some(schedule) = &payment_schedule {
+        validate_payment_schedule(price, schedule)?;
     }
+    validate_images(&images)?;
+    validate_text("store_name", &store_name, MAX_NAME_LEN)?;
+    validate_expiry(&_env, expires_at)?;
 
-    for offer_id in request.offer_ids.iter() {
-        let mut offer = OFFERS.load(deps.storage, *offer_id)?;
-        offer.is_accepted = false;
-        OFFERS.save(deps.storage, offer.id, &offer)?;
+    let request = REQUESTS.load(deps.storage, offer.request_id)?;
+
+    match request.lifecycle {
+        RequestLifecycle::Pending | RequestLifecycle::AcceptedBySeller => {}
+        RequestLifecycle::Cancelled => return Err(MarketplaceError::RequestCancelled),
+        _ => return Err(MarketplaceError::RequestLocked),
     }
 
-    offer.is_accepted = true;
-    offer.updated_at = _env.block.time.seconds();
-    request.lifecycle = RequestLifecycle::AcceptedByBuyer;
-    request.locked_seller_id = offer.seller_id;
-    request.seller_price_quote = offer.price;
+    if request.is_expired(_env.block.time.seconds()) {
+        return Err(MarketplaceError::RequestExpired);
+    }
+
+    if request.auction.is_some() {
+        return Err(MarketplaceError::SealedBidsOnly);
+    }
+
+    check_offer_price(deps.as_ref(), &request, price, &line_quotes)?;
 
+    let now = _env.block.time.seconds();
+    if let Some(pending) = offer
+        .revisions
+        .last_mut()
+        .filter(|revision| revision.status == RevisionStatus::Proposed)
+    {
+        pending.status = RevisionStatus::Rejected;
+    }
+    if price != offer.price {
+        start_revisions(&mut offer);
+        offer.revisions.push(OfferRevision {
+            price,
+            proposed_by: AccountType::Seller,
+            status: RevisionStatus::Accepted,
+            created_at: now,
+        });
+    }
+    offer.price = price;
+    offer.images = images;
+    offer.store_name = store_name;
+    offer.expires_at = expires_at;
+    offer.payment_schedule = payment_schedule;
+    offer.line_quotes = line_quotes;
+    offer.updated_at = now;
     OFFERS.save(deps.storage, offer.id, &offer)?;
-    REQUESTS.save(deps.storage, request.id, &request)?;
 
-    Ok(Response::new().add_attribute("method", "accept_offer"))
+    Ok(Response::new()
+        .add_attribute("method", "update_offer")
+        .add_event(events::offer_updated(&offer)))
 }
 
-pub fn delete_request(
+/// Adds a round to the offer's negotiation. A counter from the other party
+/// rejects their pending proposal.
+pub fn counter_offer(
     deps: DepsMut,
     info: MessageInfo,
     _env: Env,
-    request_id: u64,
+    offer_id: u64,
+    price: u128,
 ) -> Result<Response, MarketplaceError> {
-    let request = REQUESTS.load(deps.storage, request_id)?;
-    let user = USERS.load(deps.storage, info.sender.as_bytes())?;
+    let (request, mut offer, party) = load_negotiation(deps.as_ref(), &info, &_env, offer_id)?;
 
-    if user.id != request.buyer_id {
-        return Err(MarketplaceError::UnauthorizedBuyer);
+    validate_price(price)?;
+
+    match offer.pending_revision() {
+        Some(pending) if pending.proposed_by == party => {
+            return Err(MarketplaceError::CounterOfferPending)
+        }
+        None if party == AccountType::Seller => {
+            return Err(MarketplaceError::NoPendingCounterOffer)
+        }
+        _ => {}
     }
 
-    if request.lifecycle != RequestLifecycle::Pending {
-        return Err(MarketplaceError::RequestLocked);
+    if party == AccountType::Seller {
+        if let Some(budget) = &request.budget {
+            let config = CONFIG.load(deps.storage)?;
+            if !within_budget(deps.as_ref(), &config, budget, price)? {
+                return Err(MarketplaceError::OfferAboveBudget);
+            }
+        }
     }
 
-    REQUESTS.remove(deps.storage, request_id);
+    let now = _env.block.time.seconds();
+    reject_pending_revision(&mut offer);
+    start_revisions(&mut offer);
+    let revision = OfferRevision {
+        price,
+        proposed_by: party,
+        status: RevisionStatus::Proposed,
+        created_at: now,
+    };
+    offer.revisions.push(revision.clone());
+    offer.updated_at = now;
+    OFFERS.save(deps.storage, offer.id, &offer)?;
 
-    Ok(Response::new().add_attribute("method", "delete_request"))
+    Ok(Response::new()
+        .add_attribute("method", "counter_offer")
+        .add_event(events::offer_countered(&offer, &revision, &info.sender)))
 }
 
-pub fn toggle_location(
-    deps: DepsMut,
-    info: MessageInfo,
-    _env: Env,
-    enabled: bool,
-) -> Result<Response, MarketplaceError> {
-    let mut user = USERS.load(deps.storage, info.sender.as_bytes())?;
-    user.location_enabled = enabled;
-
-    USERS.save(deps.storage, info.sender.as_bytes(), &user)?;
+/// Seeds an offer's history with the seller's original quote before its
+/// price first changes.
+fn start_revisions(offer: &mut Offer) {
+    if offer.revisions.is_empty() {
+        offer.revisions.push(OfferRevision {
+            price: offer.price,
+            proposed_by: AccountType::Seller,
+            status: RevisionStatus::Accepted,
+            created_at: offer.created_at,
+        });
+    }
+}
 
-    Ok(Response::new().add_attribute("method", "toggle_location"))
+/// Closes the pending counter-offer, if any, without agreeing to its price.
+fn reject_pending_revision(offer: &mut Offer) {
+    if let Some(pending) = offer
+        .revisions
+        .last_mut()
+        .filter(|revision| revision.status == RevisionStatus::Proposed)
+    {
+        pending.status = RevisionStatus::Rejected;
+    }
 }
 
-pub fn mark_request_as_completed(
+/// Settles the pending counter-offer. Accepting it makes its price the
+/// offer's agreed price.
+pub fn respond_to_counter_offer(
     deps: DepsMut,
     info: MessageInfo,
     _env: Env,
-    request_id: u64,
+    offer_id: u64,
+    accept: bool,
 ) -> Result<Response, MarketplaceError> {
-    let mut request = REQUESTS.load(deps.storage, request_id)?;
-    let user = USERS.load(deps.storage, info.sender.as_bytes())?;
-    let payment_info = PAYMENT_INFO.load(deps.storage, request_id)?;
-
-    if user.id != request.buyer_id {
-        return Err(MarketplaceError::UnauthorizedBuyer);
+    let (_, mut offer, party) = load_negotiation(deps.as_ref(), &info, &_env, offer_id)?;
+
+    let proposed_by = offer
+        .pending_revision()
+        .map(|pending| pending.proposed_by.clone())
+        .ok_or(MarketplaceError::NoPendingCounterOffer)?;
+    if proposed_by == party {
+        return Err(MarketplaceError::CounterOfferPending);
     }
 
-    if request.lifecycle != RequestLifecycle::Paid {
-        return Err(MarketplaceError::RequestNotAccepted);
+    let now = _env.block.time.seconds();
+    let pending = offer
+        .revisions
+        .last_mut()
+        .ok_or(MarketplaceError::NoPendingCounterOffer)?;
+    if accept {
+        pending.status = RevisionStatus::Accepted;
+        offer.price = pending.price;
+    } else {
+        pending.status = RevisionStatus::Rejected;
     }
+    let revision = pending.clone();
+    offer.updated_at = now;
+    OFFERS.save(deps.storage, offer.id, &offer)?;
 
-    if request.updated_at.checked_add(TIME_TO_LOCK).unwrap() > _env.block.time.seconds() {
-        return Err(MarketplaceError::RequestNotLocked);
-    }
+    Ok(Response::new()
+        .add_attribute("method", "respond_to_counter_offer")
+        .add_event(events::counter_offer_answered(
+            &offer,
+            &revision,
+            &info.sender,
+        )))
+}
 
-    if user.account_type != AccountType::Buyer {
-        return Err(MarketplaceError::OnlyBuyersAllowed);
-    }
+/// Loads an offer that is still open for negotiation, along with the side the
+/// sender is on.
+fn load_negotiation(
+    deps: Deps,
+    info: &MessageInfo,
+    env: &Env,
+    offer_id: u64,
+) -> Result<(Request, Offer, AccountType), MarketplaceError> {
+    let offer = OFFERS.load(deps.storage, offer_id)?;
+    let request = REQUESTS.load(deps.storage, offer.request_id)?;
+    let now = env.block.time.seconds();
 
-    request.lifecycle = RequestLifecycle::Completed;
-    request.updated_at = _env.block.time.seconds();
+    let party = if info.sender == offer.authority {
+        ensure_not_suspended(deps, offer.seller_id)?;
+        AccountType::Seller
+    } else {
+        let user = USERS.load(deps.storage, info.sender.as_bytes())?;
+        if user.id != request.buyer_id {
+            return Err(MarketplaceError::Unauthorized);
+        }
+        ensure_not_suspended(deps, user.id)?;
+        AccountType::Buyer
+    };
 
-    REQUESTS.save(deps.storage, request_id, &request)?;
+    match request.lifecycle {
+        RequestLifecycle::Pending | RequestLifecycle::AcceptedBySeller => {}
+        RequestLifecycle::Cancelled => return Err(MarketplaceError::RequestCancelled),
+        _ => return Err(MarketplaceError::RequestLocked),
+    }
 
-    let amount: u128 = payment_info.amount.into();
-
-    if amount > 0 {
-        if payment_info.coin == CoinPayment::USDT {
-            // Transfer USDT
-            let transfer_msg: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: USDT_ADDR.to_string(),
-                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
-                    recipient: payment_info.seller.to_string(),
-                    amount: payment_info.amount.into(),
-                })?,
-                funds: vec![],
-            });
-
-            return Ok(Response::new()
-                .add_message(transfer_msg)
-                .add_attribute("method", "mark_request_as_completed"));
-        } else if payment_info.coin == CoinPayment::Cosmos {
-            // Transfer native tokens
-            let transfer_msg: CosmosMsg = CosmosMsg::Bank(cosmwasm_std::BankMsg::Send {
-                to_address: payment_info.seller.to_string(),
-                amount: vec![Coin {
-                    denom: COIN_DENOM.to_string(), // Replace with the correct denom
-                    amount: payment_info.amount,
-                }],
-            });
+    if request.is_expired(now) {
+        return Err(MarketplaceError::RequestExpired);
+    }
 
-            return Ok(Response::new()
-                .add_message(transfer_msg)
-                .add_attribute("method", "mark_request_as_completed"));
-        } else {
-            return Err(MarketplaceError::UnknownPaymentType);
-        }
+    if offer.is_expired(now) {
+        return Err(MarketplaceError::OfferExpired);
+    }
+
+    if request.auction.is_some()
+        || offer.commitment.is_some()
+        || offer.payment_schedule.is_some()
+        || !offer.line_quotes.is_empty()
+    {
+        return Err(MarketplaceError::NotNegotiable);
     }
 
-    Ok(Response::new().add_attribute("method", "mark_request_as_completed"))
+    Ok((request, offer, party))
 }
 
-pub fn pay_for_request_token(
+pub fn accept_offer(
     deps: DepsMut,
-    env: Env,
     info: MessageInfo,
-    request_id: u64,
-    coin: CoinPayment,
+    _env: Env,
+    offer_id: u64,
+    lines: Option<Vec<u32>>,
 ) -> Result<Response, MarketplaceError> {
-    let mut request = REQUESTS.load(deps.storage, request_id)?;
-    let offer_id = request.accepted_offer_id;
-    let offer = OFFERS.load(deps.storage, offer_id)?;
-
-    let user = USERS.load(deps.storage, info.sender.as_bytes())?;
+    let mut offer = OFFERS.load(deps.storage, offer_id)?;
+    let buyer = USERS.load(deps.storage, info.sender.as_bytes())?;
+    let mut request = REQUESTS.load(deps.storage, offer.request_id)?;
 
-    if request.paid {
-        return Err(MarketplaceError::RequestAlreadyPaid);
+    if buyer.account_type != AccountType::Buyer {
+        return Err(MarketplaceError::OnlyBuyersAllowed);
     }
 
-    if request.buyer_id != user.id {
+    if request.buyer_id != buyer.id {
         return Err(MarketplaceError::UnauthorizedBuyer);
     }
-    if request.lifecycle != RequestLifecycle::AcceptedByBuyer {
-        return Err(MarketplaceError::RequestNotAccepted);
+    ensure_not_suspended(deps.as_ref(), buyer.id)?;
+
+    if !request.offer_ids.contains(&offer.id) || offer.hidden {
+        return Err(MarketplaceError::OfferNotFound);
     }
-    if request.updated_at + TIME_TO_LOCK > env.block.time.seconds() {
-        return Err(MarketplaceError::RequestNotLocked);
+    ensure_not_suspended(deps.as_ref(), offer.seller_id)?;
+
+    if offer.is_accepted {
+        return Err(MarketplaceError::OfferAlreadyAccepted);
     }
 
-    if !offer.is_accepted {
-        return Err(MarketplaceError::RequestNotAccepted);
+    if offer.is_expired(_env.block.time.seconds()) {
+        return Err(MarketplaceError::OfferExpired);
     }
 
-    request.paid = true;
-    request.lifecycle = RequestLifecycle::Paid;
+    if request.is_expired(_env.block.time.seconds()) {
+        return Err(MarketplaceError::RequestExpired);
+    }
 
-    let mut new_payment_info = PaymentInfo {
-        buyer: info.sender.clone(),
-        request_id,
-        seller: offer.authority.clone(),
-        authority: info.sender.clone(),
-        amount: Uint128::zero(),
-        coin: coin.clone(),
-        created_at: env.block.time.seconds(),
-        updated_at: env.block.time.seconds(),
+    if let Some(auction) = &request.auction {
+        if auction.auto_select {
+            return Err(MarketplaceError::AutoSelectAuction);
+        }
+        if _env.block.time.seconds() < auction.reveal_ends_at {
+            return Err(MarketplaceError::AuctionNotEnded);
+        }
+        if offer.commitment.is_some() {
+            return Err(MarketplaceError::OfferNotRevealed);
+        }
+    }
+
+    // The buyer may switch to another offer until the lock window has passed since
+    // the last acceptance; after that (or once paid) the request is locked.
+    match request.lifecycle_at(_env.block.time.seconds()) {
+        RequestLifecycle::Pending
+        | RequestLifecycle::AcceptedBySeller
+        | RequestLifecycle::AcceptedByBuyer => {}
+        RequestLifecycle::Cancelled => return Err(MarketplaceError::RequestCancelled),
+        _ => return Err(MarketplaceError::RequestLocked),
+    }
+
+    let accepted_lines = match lines {
+        Some(lines) => select_lines(&offer, lines)?,
+        None => vec![],
     };
+    mark_accepted(
+        deps.storage,
+        &_env,
+        &mut request,
+        &mut offer,
+        accepted_lines,
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("method", "accept_offer")
+        .add_event(events::offer_accepted(&offer, &info.sender)))
+}
 
-    if coin == CoinPayment::USDT {
-        //TODO: Calculate amount based on a price feed or some other logic
-        let usdt_amount = offer.price; // Assuming price calculation logic is done elsewhere
-        new_payment_info.amount = Uint128::from(usdt_amount);
-
-        // Transfer USDT
-        let transfer_msg = CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: USDT_ADDR.to_string(),
-            msg: to_json_binary(&Cw20ExecuteMsg::TransferFrom {
-                owner: info.sender.to_string(),
-                recipient: env.contract.address.to_string(),
-                amount: usdt_amount.into(),
-            })?,
-            funds: vec![],
-        });
+/// Makes `offer` the request's only accepted offer and starts the lock window.
+/// Counter-offers still pending on the request are rejected, so the agreed
+/// price cannot change afterwards.
+fn mark_accepted(
+    storage: &mut dyn Storage,
+    env: &Env,
+    request: &mut Request,
+    offer: &mut Offer,
+    accepted_lines: Vec<u32>,
+) -> StdResult<()> {
+    for offer_id in request.offer_ids.iter() {
+        let mut offer = OFFERS.load(storage, *offer_id)?;
+        offer.is_accepted = false;
+        offer.accepted_lines = vec![];
+        reject_pending_revision(&mut offer);
+        OFFERS.save(storage, offer.id, &offer)?;
+    }
 
-        PAYMENT_INFO.save(deps.storage, request_id, &new_payment_info)?;
+    reject_pending_revision(offer);
+    offer.is_accepted = true;
+    offer.accepted_lines = accepted_lines;
+    offer.updated_at = env.block.time.seconds();
+    request.lifecycle = RequestLifecycle::AcceptedByBuyer;
+    request.locked_seller_id = offer.seller_id;
+    request.seller_price_quote = offer.accepted_price();
+    request.accepted_offer_id = offer.id;
+    request.updated_at = env.block.time.seconds();
 
-        Ok(Response::new().add_message(transfer_msg).add_event(
-            cosmwasm_std::Event::new("request_payment_transacted")
-                .add_attribute("amount", new_payment_info.amount.to_string())
-                .add_attribute("coin", format!("{:?}", coin))
-                .add_attribute("request_id", request_id.to_string())
-                .add_attribute("authority", offer.authority.to_string())
-                .add_attribute("buyer", info.sender.to_string()),
-        ))
-    } else {
-        Err(MarketplaceError::UnknownPaymentType)
+    OFFERS.save(storage, offer.id, offer)?;
+    REQUESTS.save(storage, request.id, request)
+}
+
+fn validate_auction(
+    env: &Env,
+    auction: &SealedAuction,
+    expires_at: Option<u64>,
+) -> Result<(), MarketplaceError> {
+    if auction.bidding_ends_at <= env.block.time.seconds()
+        || auction.reveal_ends_at <= auction.bidding_ends_at
+        // The request must outlive the reveal window, or it could never be
+        // settled or accepted.
+        || expires_at.is_some_and(|expires_at| expires_at <= auction.reveal_ends_at)
+    {
+        return Err(MarketplaceError::InvalidAuctionWindow);
     }
+    Ok(())
 }
 
-pub fn pay_for_request(
+/// Places a sealed bid: the price stays hidden until `reveal_offer`.
+pub fn commit_offer(
     deps: DepsMut,
-    env: Env,
     info: MessageInfo,
+    env: Env,
     request_id: u64,
-    coin: CoinPayment,
+    commitment: HexBinary,
+    images: Vec<String>,
+    store_name: String,
 ) -> Result<Response, MarketplaceError> {
-    let mut request = REQUESTS.load(deps.storage, request_id)?;
-    let offer_id = request.accepted_offer_id;
-    let offer = OFFERS.load(deps.storage, offer_id)?;
+    let offer_count = OFFER_COUNT.load(deps.storage)?;
     let user = USERS.load(deps.storage, info.sender.as_bytes())?;
 
-    if request.paid {
-        return Err(MarketplaceError::RequestAlreadyPaid);
+    if user.account_type != AccountType::Seller {
+        return Err(MarketplaceError::OnlySellersAllowed);
     }
-    if request.buyer_id != user.id {
-        return Err(MarketplaceError::UnauthorizedBuyer);
+    ensure_not_suspended(deps.as_ref(), user.id)?;
+    ensure_staked(deps.as_ref(), user.id)?;
+
+    if commitment.len() != 32 {
+        return Err(MarketplaceError::InvalidCommitment);
     }
-    if request.lifecycle != RequestLifecycle::AcceptedByBuyer {
-        return Err(MarketplaceError::RequestNotAccepted);
+    validate_images(&images)?;
+    validate_text("store_name", &store_name, MAX_NAME_LEN)?;
+
+    let mut request = REQUESTS.load(deps.storage, request_id)?;
+
+    if request.lifecycle == RequestLifecycle::Cancelled {
+        return Err(MarketplaceError::RequestCancelled);
     }
-    if request.updated_at + TIME_TO_LOCK > env.block.time.seconds() {
-        return Err(MarketplaceError::RequestNotLocked);
+
+    let auction = request
+        .auction
+        .as_ref()
+        .ok_or(MarketplaceError::NotAnAuction)?;
+    if env.block.time.seconds() >= auction.bidding_ends_at {
+        return Err(MarketplaceError::BiddingClosed);
     }
 
-    if !offer.is_accepted {
-        return Err(MarketplaceError::RequestNotAccepted);
+    ensure_offer_slot(deps.as_ref(), &env, &request, user.id)?;
+
+    if request.lifecycle == RequestLifecycle::Pending {
+        request.lifecycle = RequestLifecycle::AcceptedBySeller;
     }
 
-    request.paid = true;
-    request.lifecycle = RequestLifecycle::Paid;
+    if !request.seller_ids.contains(&user.id) {
+        request.seller_ids.push(user.id);
+    }
+    request.offer_ids.push(offer_count);
 
-    let mut new_payment_info = PaymentInfo {
-        buyer: info.sender.clone(),
-        request_id,
-        authority: info.sender.clone(),
-        seller: offer.authority.clone(),
-        amount: Uint128::zero(),
-        coin: coin.clone(),
+    REQUESTS.save(deps.storage, request_id, &request)?;
+
+    let offer = Offer {
+        id: offer_count,
+        price: 0,
+        images,
+        request_id,
+        store_name,
+        seller_id: user.id,
+        is_accepted: false,
+        is_cancelled: false,
         created_at: env.block.time.seconds(),
         updated_at: env.block.time.seconds(),
+        authority: info.sender.clone(),
+        expires_at: None,
+        payment_schedule: None,
+        line_quotes: vec![],
+        accepted_lines: vec![],
+        commitment: Some(commitment),
+        revisions: vec![],
+        hidden: false,
+    };
+
+    OFFERS.save(deps.storage, offer.id, &offer)?;
+    OFFER_COUNT.save(deps.storage, &(offer_count + 1))?;
+
+    Ok(Response::new()
+        .add_attribute("method", "commit_offer")
+        .add_event(events::offer_committed(&offer)))
+}
+
+pub fn reveal_offer(
+    deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+    offer_id: u64,
+    price: u128,
+    salt: String,
+) -> Result<Response, MarketplaceError> {
+    let mut offer = OFFERS.load(deps.storage, offer_id)?;
+
+    if info.sender != offer.authority {
+        return Err(MarketplaceError::UnauthorizedSeller);
+    }
+    ensure_not_suspended(deps.as_ref(), offer.seller_id)?;
+
+    let commitment = offer
+        .commitment
+        .as_ref()
+        .ok_or(MarketplaceError::OfferNotSealed)?;
+
+    let request = REQUESTS.load(deps.storage, offer.request_id)?;
+
+    if request.lifecycle == RequestLifecycle::Cancelled {
+        return Err(MarketplaceError::RequestCancelled);
+    }
+
+    let auction = request
+        .auction
+        .as_ref()
+        .ok_or(MarketplaceError::NotAnAuction)?;
+    let now = env.block.time.seconds();
+    if now < auction.bidding_ends_at || now >= auction.reveal_ends_at {
+        return Err(MarketplaceError::NotInRevealWindow);
+    }
+
+    if bid_commitment(request.id, info.sender.as_str(), price, &salt) != *commitment {
+        return Err(MarketplaceError::CommitmentMismatch);
+    }
+
+    validate_price(price)?;
+    if let Some(budget) = &request.budget {
+        let config = CONFIG.load(deps.storage)?;
+        if !within_budget(deps.as_ref(), &config, budget, price)? {
+            return Err(MarketplaceError::OfferAboveBudget);
+        }
+    }
+
+    offer.price = price;
+    offer.commitment = None;
+    offer.updated_at = now;
+    OFFERS.save(deps.storage, offer.id, &offer)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "reveal_offer")
+        .add_event(events::offer_revealed(&offer)))
+}
+
+/// Accepts the lowest revealed bid (the earliest one on a tie) once the reveal
+/// window of an `auto_select` auction has closed.
+pub fn settle_auction(
+    deps: DepsMut,
+    env: Env,
+    request_id: u64,
+) -> Result<Response, MarketplaceError> {
+    let mut request = REQUESTS.load(deps.storage, request_id)?;
+
+    let auction = request
+        .auction
+        .as_ref()
+        .ok_or(MarketplaceError::NotAnAuction)?;
+    if !auction.auto_select {
+        return Err(MarketplaceError::ManualSelectAuction);
+    }
+    if env.block.time.seconds() < auction.reveal_ends_at {
+        return Err(MarketplaceError::AuctionNotEnded);
+    }
+
+    if request.is_expired(env.block.time.seconds()) {
+        return Err(MarketplaceError::RequestExpired);
+    }
+
+    match request.lifecycle {
+        RequestLifecycle::Pending | RequestLifecycle::AcceptedBySeller => {}
+        RequestLifecycle::Cancelled => return Err(MarketplaceError::RequestCancelled),
+        _ => return Err(MarketplaceError::RequestLocked),
+    }
+
+    let winner = best_offer(
+        deps.storage,
+        &request,
+        env.block.time.seconds(),
+        &AcceptStrategy::LowestPrice,
+    )?;
+    let mut offer = winner.ok_or(MarketplaceError::NoRevealedOffers)?;
+
+    mark_accepted(deps.storage, &env, &mut request, &mut offer, vec![])?;
+    let buyer = USERS_BY_ID.load(deps.storage, request.buyer_id)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "settle_auction")
+        .add_event(events::offer_accepted(&offer, &buyer.authority)))
+}
+
+/// Accepts the best offer for the buyer once the `auto_accept` deadline has
+/// passed, with the same checks as `accept_offer`.
+pub fn finalize_request(
+    deps: DepsMut,
+    env: Env,
+    request_id: u64,
+) -> Result<Response, MarketplaceError> {
+    let mut request = REQUESTS.load(deps.storage, request_id)?;
+    let now = env.block.time.seconds();
+
+    let strategy = match &request.auto_accept {
+        Some(auto_accept) if now < auto_accept.deadline => {
+            return Err(MarketplaceError::DeadlineNotReached)
+        }
+        Some(auto_accept) => auto_accept.strategy.clone(),
+        None => return Err(MarketplaceError::AutoAcceptNotEnabled),
+    };
+
+    if request.is_expired(now) {
+        return Err(MarketplaceError::RequestExpired);
+    }
+
+    match request.lifecycle {
+        RequestLifecycle::Pending | RequestLifecycle::AcceptedBySeller => {}
+        RequestLifecycle::Cancelled => return Err(MarketplaceError::RequestCancelled),
+        _ => return Err(MarketplaceError::RequestLocked),
+    }
+    ensure_not_suspended(deps.as_ref(), request.buyer_id)?;
+
+    let winner = best_offer(deps.storage, &request, now, &strategy)?;
+    let mut offer = winner.ok_or(MarketplaceError::NoEligibleOffers)?;
+
+    mark_accepted(deps.storage, &env, &mut request, &mut offer, vec![])?;
+    let buyer = USERS_BY_ID.load(deps.storage, request.buyer_id)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "finalize_request")
+        .add_event(events::offer_accepted(&offer, &buyer.authority)))
+}
+
+/// Picks the winning offer among those the buyer could still accept, so never
+/// one from a suspended seller. Ties go to the earliest offer.
+fn best_offer(
+    storage: &dyn Storage,
+    request: &Request,
+    now: u64,
+    strategy: &AcceptStrategy,
+) -> StdResult<Option<Offer>> {
+    let mut best: Option<(u64, Offer)> = None;
+    for offer_id in request.offer_ids.iter() {
+        let offer = OFFERS.load(storage, *offer_id)?;
+        if offer.commitment.is_some()
+            || offer.is_cancelled
+            || offer.hidden
+            || offer.is_expired(now)
+            || SUSPENDED_USERS.has(storage, offer.seller_id)
+        {
+            continue;
+        }
+        let sales = match strategy {
+            AcceptStrategy::LowestPrice => 0,
+            AcceptStrategy::HighestRatedSeller => SELLER_SALES
+                .may_load(storage, offer.seller_id)?
+                .unwrap_or_default(),
+        };
+        let better = best.as_ref().is_none_or(|(best_sales, best)| {
+            sales > *best_sales || (sales == *best_sales && offer.price < best.price)
+        });
+        if better {
+            best = Some((sales, offer));
+        }
+    }
+    Ok(best.map(|(_, offer)| offer))
+}
+
+fn within_budget(
+    deps: Deps,
+    config: &Config,
+    budget: &Budget,
+    price: u128,
+) -> Result<bool, MarketplaceError> {
+    let cost = match &budget.coin {
+        Some(coin) => oracle::convert(&deps.querier, config, coin, Uint128::from(price))?,
+        None => Uint128::from(price),
+    };
+    Ok(cost <= Uint128::from(budget.max_price))
+}
+
+/// Validates the buyer's pick of quoted lines. Picking no line or every line is
+/// the same as accepting the whole offer and is stored as such.
+fn select_lines(offer: &Offer, mut lines: Vec<u32>) -> Result<Vec<u32>, MarketplaceError> {
+    lines.sort_unstable();
+    lines.dedup();
+    for line in &lines {
+        if !offer.line_quotes.iter().any(|quote| quote.line == *line) {
+            return Err(MarketplaceError::LineNotQuoted { line: *line });
+        }
+    }
+
+    if lines.is_empty() || lines.len() == offer.line_quotes.len() {
+        return Ok(vec![]);
+    }
+    if offer.payment_schedule.is_some() {
+        return Err(MarketplaceError::PartialAcceptanceWithSchedule);
+    }
+    Ok(lines)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    time_to_lock: Option<u64>,
+    min_time_to_lock: Option<u64>,
+    max_time_to_lock: Option<u64>,
+    oracle: Option<String>,
+    min_stake: Option<Uint128>,
+    unbonding_period: Option<u64>,
+    max_open_requests: Option<u32>,
+    max_offers_per_request: Option<u32>,
+    max_sellers_per_request: Option<u32>,
+    creation_fee: Option<Uint128>,
+    auto_release_after: Option<u64>,
+) -> Result<Response, MarketplaceError> {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    if info.sender != config.admin {
+        return Err(MarketplaceError::Unauthorized);
+    }
+
+    if let Some(time_to_lock) = time_to_lock {
+        config.time_to_lock = time_to_lock;
+    }
+    if let Some(min_time_to_lock) = min_time_to_lock {
+        config.min_time_to_lock = min_time_to_lock;
+    }
+    if let Some(max_time_to_lock) = max_time_to_lock {
+        config.max_time_to_lock = max_time_to_lock;
+    }
+    if let Some(oracle) = oracle {
+        config.oracle = Some(deps.api.addr_validate(&oracle)?);
+    }
+    if let Some(min_stake) = min_stake {
+        config.min_stake = min_stake;
+    }
+    if let Some(unbonding_period) = unbonding_period {
+        config.unbonding_period = unbonding_period;
+    }
+    if let Some(max_open_requests) = max_open_requests {
+        config.max_open_requests = max_open_requests;
+    }
+    if let Some(max_offers_per_request) = max_offers_per_request {
+        config.max_offers_per_request = max_offers_per_request;
+    }
+    if let Some(max_sellers_per_request) = max_sellers_per_request {
+        config.max_sellers_per_request = max_sellers_per_request;
+    }
+    if let Some(creation_fee) = creation_fee {
+        config.creation_fee = creation_fee;
+    }
+    if let Some(auto_release_after) = auto_release_after {
+        config.auto_release_after = auto_release_after;
+    }
+    validate_config(&config)?;
+
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "update_config")
+        .add_event(events::config_updated(&config)))
+}
+
+fn validate_config(config: &Config) -> Result<(), MarketplaceError> {
+    if config.min_time_to_lock > config.time_to_lock
+        || config.time_to_lock > config.max_time_to_lock
+    {
+        return Err(MarketplaceError::InvalidLockWindow);
+    }
+    Ok(())
+}
+
+fn validate_expiry(env: &Env, expires_at: Option<u64>) -> Result<(), MarketplaceError> {
+    match expires_at {
+        Some(expires_at) if expires_at <= env.block.time.seconds() => {
+            Err(MarketplaceError::InvalidExpiry)
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn delete_request(
+    deps: DepsMut,
+    info: MessageInfo,
+    _env: Env,
+    request_id: u64,
+) -> Result<Response, MarketplaceError> {
+    let request = load_cancellable_request(deps.as_ref(), &info, request_id, false)?;
+
+    let offer_events = close_request_offers(deps.storage, &_env, &request, true)?;
+    REQUESTS.remove(deps.storage, request_id);
+
+    let response = Response::new()
+        .add_attribute("method", "delete_request")
+        .add_event(events::request_deleted(&request, &info.sender))
+        .add_events(offer_events);
+    Ok(close_request(
+        deps.storage,
+        &request,
+        &info.sender,
+        false,
+        response,
+    )?)
+}
+
+/// Soft-delete: the request and its offers stay in storage for audits but are
+/// marked cancelled and can no longer be acted upon. Unlike `delete_request`
+/// this also works once an offer is accepted, as long as nothing was paid.
+pub fn cancel_request(
+    deps: DepsMut,
+    info: MessageInfo,
+    _env: Env,
+    request_id: u64,
+) -> Result<Response, MarketplaceError> {
+    let mut request = load_cancellable_request(deps.as_ref(), &info, request_id, true)?;
+
+    let offer_events = close_request_offers(deps.storage, &_env, &request, false)?;
+    request.lifecycle = RequestLifecycle::Cancelled;
+    request.updated_at = _env.block.time.seconds();
+    REQUESTS.save(deps.storage, request_id, &request)?;
+
+    let response = Response::new()
+        .add_attribute("method", "cancel_request")
+        .add_event(events::request_cancelled(&request, &info.sender))
+        .add_events(offer_events);
+    Ok(close_request(
+        deps.storage,
+        &request,
+        &info.sender,
+        false,
+        response,
+    )?)
+}
+
+fn load_cancellable_request(
+    deps: Deps,
+    info: &MessageInfo,
+    request_id: u64,
+    allow_accepted: bool,
+) -> Result<Request, MarketplaceError> {
+    let request = REQUESTS.load(deps.storage, request_id)?;
+    let user = USERS.load(deps.storage, info.sender.as_bytes())?;
+
+    if user.id != request.buyer_id {
+        return Err(MarketplaceError::UnauthorizedBuyer);
+    }
+
+    match request.lifecycle {
+        RequestLifecycle::Pending | RequestLifecycle::AcceptedBySeller => Ok(request),
+        RequestLifecycle::AcceptedByBuyer | RequestLifecycle::RequestLocked if allow_accepted => {
+            Ok(request)
+        }
+        RequestLifecycle::Cancelled => Err(MarketplaceError::RequestCancelled),
+        _ => Err(MarketplaceError::RequestLocked),
+    }
+}
+
+/// Removes (or marks cancelled) every offer linked to `request` and returns one
+/// event per affected seller listing their offers.
+fn close_request_offers(
+    storage: &mut dyn Storage,
+    env: &Env,
+    request: &Request,
+    remove: bool,
+) -> StdResult<Vec<Event>> {
+    let mut offers_by_seller: Vec<(Addr, Vec<u64>)> = vec![];
+
+    for offer_id in request.offer_ids.iter() {
+        let mut offer = match OFFERS.may_load(storage, *offer_id)? {
+            Some(offer) => offer,
+            None => continue,
+        };
+
+        if remove {
+            OFFERS.remove(storage, offer.id);
+        } else {
+            offer.is_cancelled = true;
+            offer.updated_at = env.block.time.seconds();
+            OFFERS.save(storage, offer.id, &offer)?;
+        }
+
+        match offers_by_seller
+            .iter_mut()
+            .find(|(seller, _)| *seller == offer.authority)
+        {
+            Some((_, offer_ids)) => offer_ids.push(offer.id),
+            None => offers_by_seller.push((offer.authority, vec![offer.id])),
+        }
+    }
+
+    Ok(offers_by_seller
+        .iter()
+        .map(|(seller, offer_ids)| events::offers_cancelled(request.id, seller, offer_ids))
+        .collect())
+}
+
+pub fn toggle_location(
+    deps: DepsMut,
+    info: MessageInfo,
+    _env: Env,
+    enabled: bool,
+) -> Result<Response, MarketplaceError> {
+    let mut user = USERS.load(deps.storage, info.sender.as_bytes())?;
+    user.location_enabled = enabled;
+
+    USERS.save(deps.storage, info.sender.as_bytes(), &user)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "toggle_location")
+        .add_event(events::location_toggled(&user)))
+}
+
+pub fn mark_request_as_completed(
+    deps: DepsMut,
+    info: MessageInfo,
+    _env: Env,
+    request_id: u64,
+) -> Result<Response, MarketplaceError> {
+    let mut request = REQUESTS.load(deps.storage, request_id)?;
+    let user = USERS.load(deps.storage, info.sender.as_bytes())?;
+
+    if user.id != request.buyer_id {
+        return Err(MarketplaceError::UnauthorizedBuyer);
+    }
+
+    if request.lifecycle != RequestLifecycle::Paid {
+        return Err(MarketplaceError::RequestNotAccepted);
+    }
+
+    if request.locked_until().unwrap_or_default() > _env.block.time.seconds() {
+        return Err(MarketplaceError::RequestNotLocked);
+    }
+
+    if user.account_type != AccountType::Buyer {
+        return Err(MarketplaceError::OnlyBuyersAllowed);
+    }
+
+    let response = complete_request(deps.storage, &_env, &mut request)?;
+    Ok(response.add_attribute("method", "mark_request_as_completed"))
+}
+
+/// Releases the escrow of a paid request to the seller and credits the sale.
+fn complete_request(
+    storage: &mut dyn Storage,
+    env: &Env,
+    request: &mut Request,
+) -> Result<Response, MarketplaceError> {
+    let payment_info = PAYMENT_INFO.load(storage, request.id)?;
+    request.lifecycle = RequestLifecycle::Completed;
+    request.updated_at = env.block.time.seconds();
+
+    REQUESTS.save(storage, request.id, request)?;
+    SELLER_SALES.update(storage, request.locked_seller_id, |sales| -> StdResult<_> {
+        Ok(sales.unwrap_or_default() + 1)
+    })?;
+
+    let event = events::request_completed(
+        request,
+        &payment_info.seller,
+        payment_info.amount,
+        &payment_info.coin,
+    );
+    let mut response = Response::new().add_event(event);
+
+    if !payment_info.amount.is_zero() {
+        let config = CONFIG.load(storage)?;
+        response = response.add_message(transfer_msg(
+            &config,
+            &payment_info.coin,
+            &payment_info.seller,
+            payment_info.amount,
+        )?);
+    }
+
+    Ok(close_request(
+        storage,
+        request,
+        &payment_info.buyer,
+        true,
+        response,
+    )?)
+}
+
+pub fn post_delivery_milestone(
+    deps: DepsMut,
+    info: MessageInfo,
+    _env: Env,
+    request_id: u64,
+    stage: DeliveryStage,
+    tracking_ref: Option<String>,
+) -> Result<Response, MarketplaceError> {
+    let mut request = REQUESTS.load(deps.storage, request_id)?;
+
+    if request.lifecycle != RequestLifecycle::Paid {
+        return Err(MarketplaceError::RequestNotPaid);
+    }
+
+    let payment_info = PAYMENT_INFO.load(deps.storage, request_id)?;
+    if info.sender != payment_info.seller {
+        return Err(MarketplaceError::UnauthorizedSeller);
+    }
+
+    if let Some(tracking_ref) = &tracking_ref {
+        validate_text("tracking_ref", tracking_ref, MAX_TRACKING_REF_LEN)?;
+    }
+    if request
+        .delivery
+        .last()
+        .is_some_and(|last| last.stage >= stage)
+    {
+        return Err(MarketplaceError::InvalidMilestone);
+    }
+
+    let milestone = DeliveryMilestone {
+        stage,
+        tracking_ref,
+        at: _env.block.time.seconds(),
+    };
+    // `updated_at` is left alone: it anchors the buyer's lock window, which a
+    // milestone must not extend.
+    request.delivery.push(milestone.clone());
+    REQUESTS.save(deps.storage, request_id, &request)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "post_delivery_milestone")
+        .add_event(events::delivery_updated(&request, &info.sender, &milestone)))
+}
+
+/// Lets the seller complete an in-person handover by revealing the buyer's secret.
+pub fn confirm_delivery(
+    deps: DepsMut,
+    info: MessageInfo,
+    _env: Env,
+    request_id: u64,
+    secret: String,
+) -> Result<Response, MarketplaceError> {
+    let mut request = REQUESTS.load(deps.storage, request_id)?;
+
+    if request.lifecycle != RequestLifecycle::Paid {
+        return Err(MarketplaceError::RequestNotPaid);
+    }
+
+    let payment_info = PAYMENT_INFO.load(deps.storage, request_id)?;
+    if info.sender != payment_info.seller {
+        return Err(MarketplaceError::UnauthorizedSeller);
+    }
+    let code = payment_info
+        .delivery_code
+        .ok_or(MarketplaceError::DeliveryCodeNotSet)?;
+    if delivery_code(request_id, &secret) != code {
+        return Err(MarketplaceError::DeliveryCodeMismatch);
+    }
+
+    let response = complete_request(deps.storage, &_env, &mut request)?;
+    Ok(response.add_attribute("method", "confirm_delivery"))
+}
+
+/// Pays the seller out once the auto-release window after delivery has passed
+/// without the buyer completing (or disputing) the request.
+pub fn release_escrow(
+    deps: DepsMut,
+    env: Env,
+    request_id: u64,
+) -> Result<Response, MarketplaceError> {
+    let mut request = REQUESTS.load(deps.storage, request_id)?;
+
+    if request.lifecycle != RequestLifecycle::Paid {
+        return Err(MarketplaceError::RequestNotPaid);
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    match request.auto_release_at(config.auto_release_after) {
+        Some(release_at) if env.block.time.seconds() >= release_at => {}
+        _ => return Err(MarketplaceError::ReleaseNotDue),
+    }
+
+    let response = complete_request(deps.storage, &env, &mut request)?;
+    Ok(response.add_attribute("method", "release_escrow"))
+}
+
+/// Called by the seller once the buyer has missed an installment: the deposit
+/// is released to the seller and every installment paid so far is refunded.
+pub fn forfeit_deposit(
+    deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+    request_id: u64,
+) -> Result<Response, MarketplaceError> {
+    let mut request = REQUESTS.load(deps.storage, request_id)?;
+
+    if request.lifecycle != RequestLifecycle::PartiallyPaid {
+        return Err(MarketplaceError::RequestNotPartiallyPaid);
+    }
+
+    let mut payment_info = PAYMENT_INFO.load(deps.storage, request_id)?;
+
+    if info.sender != payment_info.seller {
+        return Err(MarketplaceError::UnauthorizedSeller);
+    }
+
+    match payment_info.next_due_at {
+        Some(due_at) if env.block.time.seconds() > due_at => {}
+        _ => return Err(MarketplaceError::InstallmentNotOverdue),
+    }
+
+    let deposit = payment_info.deposit;
+    let refund = payment_info
+        .amount
+        .checked_sub(deposit)
+        .map_err(StdError::from)?;
+
+    request.lifecycle = RequestLifecycle::Defaulted;
+    request.updated_at = env.block.time.seconds();
+    payment_info.next_due_at = None;
+    payment_info.updated_at = env.block.time.seconds();
+
+    REQUESTS.save(deps.storage, request_id, &request)?;
+    PAYMENT_INFO.save(deps.storage, request_id, &payment_info)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    let mut response = Response::new()
+        .add_attribute("method", "forfeit_deposit")
+        .add_event(events::deposit_forfeited(&payment_info, deposit, refund))
+        .add_message(transfer_msg(
+            &config,
+            &payment_info.coin,
+            &payment_info.seller,
+            deposit,
+        )?);
+    if !refund.is_zero() {
+        response = response.add_message(transfer_msg(
+            &config,
+            &payment_info.coin,
+            &payment_info.buyer,
+            refund,
+        )?);
+    }
+
+    Ok(close_request(
+        deps.storage,
+        &request,
+        &payment_info.buyer,
+        false,
+        response,
+    )?)
+}
+
+/// A seller keeps at most one active offer per request, makes at most
+/// `max_offers_per_request` over time, and only `max_sellers_per_request`
+/// distinct sellers may bid.
+fn ensure_offer_slot(
+    deps: Deps,
+    env: &Env,
+    request: &Request,
+    seller_id: u64,
+) -> Result<(), MarketplaceError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    let mut offers = 0;
+    for offer_id in request.offer_ids.iter() {
+        let offer = OFFERS.load(deps.storage, *offer_id)?;
+        if offer.seller_id != seller_id {
+            continue;
+        }
+        if !offer.is_cancelled && !offer.is_expired(env.block.time.seconds()) {
+            return Err(MarketplaceError::ActiveOfferExists { offer_id: offer.id });
+        }
+        offers += 1;
+    }
+    if config.max_offers_per_request > 0 && offers >= config.max_offers_per_request {
+        return Err(MarketplaceError::TooManyOffers {
+            max: config.max_offers_per_request,
+        });
+    }
+
+    if config.max_sellers_per_request > 0
+        && !request.seller_ids.contains(&seller_id)
+        && request.seller_ids.len() >= config.max_sellers_per_request as usize
+    {
+        return Err(MarketplaceError::IncorrectNumberOfSellers);
+    }
+    Ok(())
+}
+
+/// Frees the buyer's open-request slot and the seller's open escrow, and pays
+/// out the creation fee: back to the buyer when `refund`, to the admin otherwise.
+fn close_request(
+    storage: &mut dyn Storage,
+    request: &Request,
+    buyer: &Addr,
+    refund: bool,
+    response: Response,
+) -> StdResult<Response> {
+    OPEN_REQUESTS.update(storage, request.buyer_id, |open| -> StdResult<_> {
+        Ok(open.unwrap_or_default().saturating_sub(1))
+    })?;
+    // Only requests that were paid into escrow have a payment record.
+    if PAYMENT_INFO.has(storage, request.id) {
+        OPEN_ESCROWS.update(storage, request.locked_seller_id, |open| -> StdResult<_> {
+            Ok(open.unwrap_or_default().saturating_sub(1))
+        })?;
+    }
+
+    if request.creation_fee.is_zero() {
+        return Ok(response);
+    }
+    let recipient = if refund {
+        buyer.clone()
+    } else {
+        CONFIG.load(storage)?.admin
+    };
+    Ok(response
+        .add_event(events::creation_fee_released(request, &recipient))
+        .add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![Coin::new(request.creation_fee, COIN_DENOM)],
+        }))
+}
+
+/// Stake withdrawals, backing out of unpaid requests and admin or moderator
+/// actions (including `SlashStake` refunds) always go through, so funds can be
+/// recovered while the rest of the contract is paused. `ForfeitDeposit` pays
+/// escrow to the seller and buyers cannot pay installments while payments are
+/// paused, so it waits with them.
+fn ensure_not_paused(deps: Deps, msg: &ExecuteMsg) -> Result<(), MarketplaceError> {
+    let paused = PAUSED.may_load(deps.storage)?.unwrap_or_default();
+    let blocked = match msg {
+        ExecuteMsg::UnbondStake { .. }
+        | ExecuteMsg::WithdrawStake {}
+        | ExecuteMsg::CancelRequest { .. }
+        | ExecuteMsg::DeleteRequest { .. }
+        | ExecuteMsg::SlashStake { .. }
+        | ExecuteMsg::SetPause { .. }
+        | ExecuteMsg::UpdateConfig { .. }
+        | ExecuteMsg::AddModerator { .. }
+        | ExecuteMsg::RemoveModerator { .. }
+        | ExecuteMsg::SuspendUser { .. }
+        | ExecuteMsg::UnsuspendUser { .. }
+        | ExecuteMsg::SetRequestHidden { .. }
+        | ExecuteMsg::SetOfferHidden { .. } => false,
+        ExecuteMsg::PayForRequest { .. }
+        | ExecuteMsg::BondStake {}
+        | ExecuteMsg::MarkRequestAsCompleted { .. }
+        | ExecuteMsg::ConfirmDelivery { .. }
+        | ExecuteMsg::ReleaseEscrow { .. }
+        | ExecuteMsg::ForfeitDeposit { .. } => paused.all || paused.payments,
+        ExecuteMsg::CreateRequest { .. }
+        | ExecuteMsg::CreateOffer { .. }
+        | ExecuteMsg::UpdateOffer { .. }
+        | ExecuteMsg::CommitOffer { .. }
+        | ExecuteMsg::RevealOffer { .. }
+        | ExecuteMsg::CounterOffer { .. }
+        | ExecuteMsg::RespondToCounterOffer { .. } => paused.all || paused.listings,
+        _ => paused.all,
+    };
+    if blocked {
+        return Err(MarketplaceError::Paused);
+    }
+    Ok(())
+}
+
+pub fn set_pause(
+    deps: DepsMut,
+    info: MessageInfo,
+    all: Option<bool>,
+    payments: Option<bool>,
+    listings: Option<bool>,
+) -> Result<Response, MarketplaceError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    if info.sender != config.admin {
+        return Err(MarketplaceError::Unauthorized);
+    }
+
+    let mut paused = PAUSED.may_load(deps.storage)?.unwrap_or_default();
+    if let Some(all) = all {
+        paused.all = all;
+    }
+    if let Some(payments) = payments {
+        paused.payments = payments;
+    }
+    if let Some(listings) = listings {
+        paused.listings = listings;
+    }
+    PAUSED.save(deps.storage, &paused)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_pause")
+        .add_event(events::pause_updated(&paused)))
+}
+
+fn ensure_not_suspended(deps: Deps, user_id: u64) -> Result<(), MarketplaceError> {
+    if SUSPENDED_USERS.has(deps.storage, user_id) {
+        return Err(MarketplaceError::Suspended);
+    }
+    Ok(())
+}
+
+/// The admin can always moderate, alongside the moderators it appointed.
+fn ensure_moderator(deps: Deps, sender: &Addr) -> Result<(), MarketplaceError> {
+    let config = CONFIG.load(deps.storage)?;
+    if *sender != config.admin && !MODERATORS.has(deps.storage, sender) {
+        return Err(MarketplaceError::Unauthorized);
+    }
+    Ok(())
+}
+
+pub fn set_moderator(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+    enabled: bool,
+) -> Result<Response, MarketplaceError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    if info.sender != config.admin {
+        return Err(MarketplaceError::Unauthorized);
+    }
+
+    let moderator = deps.api.addr_validate(&address)?;
+    if enabled {
+        MODERATORS.save(deps.storage, &moderator, &Empty {})?;
+    } else {
+        MODERATORS.remove(deps.storage, &moderator);
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "set_moderator")
+        .add_event(events::moderator_updated(&moderator, enabled)))
+}
+
+pub fn set_suspended(
+    deps: DepsMut,
+    info: MessageInfo,
+    _env: Env,
+    address: String,
+    suspended: bool,
+) -> Result<Response, MarketplaceError> {
+    ensure_moderator(deps.as_ref(), &info.sender)?;
+
+    let addr = deps.api.addr_validate(&address)?;
+    let user = USERS.load(deps.storage, addr.as_bytes())?;
+    if suspended {
+        SUSPENDED_USERS.save(deps.storage, user.id, &_env.block.time.seconds())?;
+    } else {
+        SUSPENDED_USERS.remove(deps.storage, user.id);
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "set_suspended")
+        .add_event(events::user_suspended(&user, suspended, &info.sender)))
+}
+
+pub fn set_request_hidden(
+    deps: DepsMut,
+    info: MessageInfo,
+    request_id: u64,
+    hidden: bool,
+) -> Result<Response, MarketplaceError> {
+    ensure_moderator(deps.as_ref(), &info.sender)?;
+
+    let mut request = REQUESTS.load(deps.storage, request_id)?;
+    request.hidden = hidden;
+    REQUESTS.save(deps.storage, request_id, &request)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_request_hidden")
+        .add_event(events::request_hidden(&request, &info.sender)))
+}
+
+pub fn set_offer_hidden(
+    deps: DepsMut,
+    info: MessageInfo,
+    offer_id: u64,
+    hidden: bool,
+) -> Result<Response, MarketplaceError> {
+    ensure_moderator(deps.as_ref(), &info.sender)?;
+
+    let mut offer = OFFERS.load(deps.storage, offer_id)?;
+    offer.hidden = hidden;
+    OFFERS.save(deps.storage, offer_id, &offer)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_offer_hidden")
+        .add_event(events::offer_hidden(&offer, &info.sender)))
+}
+
+fn ensure_staked(deps: Deps, seller_id: u64) -> Result<(), MarketplaceError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.min_stake.is_zero() {
+        return Ok(());
+    }
+    let stake = SELLER_STAKES
+        .may_load(deps.storage, seller_id)?
+        .unwrap_or_default();
+    if stake.bonded < config.min_stake {
+        return Err(MarketplaceError::InsufficientStake {
+            required: config.min_stake,
+        });
+    }
+    Ok(())
+}
+
+pub fn bond_stake(
+    deps: DepsMut,
+    info: MessageInfo,
+    _env: Env,
+) -> Result<Response, MarketplaceError> {
+    let user = USERS.load(deps.storage, info.sender.as_bytes())?;
+
+    if user.account_type != AccountType::Seller {
+        return Err(MarketplaceError::OnlySellersAllowed);
+    }
+
+    if let Some(stray) = info.funds.iter().find(|c| c.denom != COIN_DENOM) {
+        return Err(MarketplaceError::UnexpectedFunds {
+            denom: stray.denom.clone(),
+        });
+    }
+    let amount: Uint128 = info.funds.iter().map(|c| c.amount).sum();
+    if amount.is_zero() {
+        return Err(MarketplaceError::InsufficientFunds);
+    }
+
+    let mut stake = SELLER_STAKES
+        .may_load(deps.storage, user.id)?
+        .unwrap_or_default();
+    stake.bonded += amount;
+    SELLER_STAKES.save(deps.storage, user.id, &stake)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "bond_stake")
+        .add_event(events::stake_bonded(&info.sender, amount, &stake)))
+}
+
+/// Moves `amount` into unbonding. Unbonding again restarts the period for the
+/// whole unbonding balance.
+pub fn unbond_stake(
+    deps: DepsMut,
+    info: MessageInfo,
+    _env: Env,
+    amount: Uint128,
+) -> Result<Response, MarketplaceError> {
+    let user = USERS.load(deps.storage, info.sender.as_bytes())?;
+    let config = CONFIG.load(deps.storage)?;
+    let mut stake = SELLER_STAKES
+        .may_load(deps.storage, user.id)?
+        .unwrap_or_default();
+
+    if amount.is_zero() || amount > stake.bonded {
+        return Err(MarketplaceError::InsufficientStake { required: amount });
+    }
+    ensure_stake_kept(deps.as_ref(), &config, user.id, stake.bonded - amount)?;
+
+    stake.bonded -= amount;
+    stake.unbonding += amount;
+    stake.unbonding_ends_at = Some(_env.block.time.seconds() + config.unbonding_period);
+    SELLER_STAKES.save(deps.storage, user.id, &stake)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "unbond_stake")
+        .add_event(events::stake_unbonding(&info.sender, amount, &stake)))
+}
+
+pub fn withdraw_stake(
+    deps: DepsMut,
+    info: MessageInfo,
+    _env: Env,
+) -> Result<Response, MarketplaceError> {
+    let user = USERS.load(deps.storage, info.sender.as_bytes())?;
+    let config = CONFIG.load(deps.storage)?;
+    let mut stake = SELLER_STAKES
+        .may_load(deps.storage, user.id)?
+        .unwrap_or_default();
+
+    if stake.unbonding.is_zero() {
+        return Err(MarketplaceError::NothingToWithdraw);
+    }
+    ensure_stake_kept(deps.as_ref(), &config, user.id, stake.bonded)?;
+    if stake
+        .unbonding_ends_at
+        .is_some_and(|ends_at| _env.block.time.seconds() < ends_at)
+    {
+        return Err(MarketplaceError::StakeStillUnbonding);
+    }
+
+    let amount = stake.unbonding;
+    stake.unbonding = Uint128::zero();
+    stake.unbonding_ends_at = None;
+    SELLER_STAKES.save(deps.storage, user.id, &stake)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "withdraw_stake")
+        .add_event(events::stake_withdrawn(&info.sender, amount))
+        .add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![Coin::new(amount, COIN_DENOM)],
+        }))
+}
+
+/// A seller holding escrow must keep `min_stake` bonded, so that it can still
+/// be slashed; `remaining` is what stays bonded after the change.
+fn ensure_stake_kept(
+    deps: Deps,
+    config: &Config,
+    seller_id: u64,
+    remaining: Uint128,
+) -> Result<(), MarketplaceError> {
+    let open_escrows = OPEN_ESCROWS
+        .may_load(deps.storage, seller_id)?
+        .unwrap_or_default();
+    if open_escrows > 0 && remaining < config.min_stake {
+        return Err(MarketplaceError::StakeLocked {
+            required: config.min_stake,
+        });
+    }
+    Ok(())
+}
+
+/// Rules against the seller of a request holding escrow: the buyer gets the
+/// escrow back plus up to `min_stake` of the seller's stake.
+pub fn slash_stake(
+    deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+    request_id: u64,
+) -> Result<Response, MarketplaceError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    if info.sender != config.admin {
+        return Err(MarketplaceError::Unauthorized);
+    }
+
+    let mut request = REQUESTS.load(deps.storage, request_id)?;
+    if !matches!(
+        request.lifecycle,
+        RequestLifecycle::PartiallyPaid | RequestLifecycle::Paid
+    ) {
+        return Err(MarketplaceError::RequestNotPaid);
+    }
+
+    let mut payment_info = PAYMENT_INFO.load(deps.storage, request_id)?;
+    let mut stake = SELLER_STAKES
+        .may_load(deps.storage, request.locked_seller_id)?
+        .unwrap_or_default();
+    let slashed = stake.slash(config.min_stake);
+    let refunded = payment_info.amount;
+
+    request.lifecycle = RequestLifecycle::Defaulted;
+    request.updated_at = env.block.time.seconds();
+    payment_info.next_due_at = None;
+    payment_info.updated_at = env.block.time.seconds();
+
+    REQUESTS.save(deps.storage, request_id, &request)?;
+    PAYMENT_INFO.save(deps.storage, request_id, &payment_info)?;
+    SELLER_STAKES.save(deps.storage, request.locked_seller_id, &stake)?;
+
+    let mut response = Response::new()
+        .add_attribute("method", "slash_stake")
+        .add_event(events::stake_slashed(&payment_info, slashed, refunded));
+    if !refunded.is_zero() {
+        response = response.add_message(transfer_msg(
+            &config,
+            &payment_info.coin,
+            &payment_info.buyer,
+            refunded,
+        )?);
+    }
+    if !slashed.is_zero() {
+        response = response.add_message(BankMsg::Send {
+            to_address: payment_info.buyer.to_string(),
+            amount: vec![Coin::new(slashed, COIN_DENOM)],
+        });
+    }
+
+    Ok(close_request(
+        deps.storage,
+        &request,
+        &payment_info.buyer,
+        true,
+        response,
+    )?)
+}
+
+/// Pays escrowed funds out of the contract.
+fn transfer_msg(
+    config: &Config,
+    coin: &CoinPayment,
+    recipient: &Addr,
+    amount: Uint128,
+) -> StdResult<CosmosMsg> {
+    let msg = match coin {
+        CoinPayment::USDT => CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: config.usdt_addr.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }),
+        CoinPayment::Cosmos => CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![Coin {
+                denom: COIN_DENOM.to_string(),
+                amount,
+            }],
+        }),
+    };
+    Ok(msg)
+}
+
+/// Checks shared by native and CW20 payments. Returns the request, its accepted
+/// offer, the payment record so far (none before the first payment) and the
+/// price due now.
+fn load_payable_request(
+    deps: Deps,
+    env: &Env,
+    info: &MessageInfo,
+    request_id: u64,
+    coin: &CoinPayment,
+) -> Result<(Request, Offer, Option<PaymentInfo>, Uint128), MarketplaceError> {
+    let request = REQUESTS.load(deps.storage, request_id)?;
+    let user = USERS.load(deps.storage, info.sender.as_bytes())?;
+
+    if request.paid {
+        return Err(MarketplaceError::RequestAlreadyPaid);
+    }
+    if request.buyer_id != user.id {
+        return Err(MarketplaceError::UnauthorizedBuyer);
+    }
+
+    let payment_info = match request.lifecycle_at(env.block.time.seconds()) {
+        RequestLifecycle::AcceptedByBuyer => return Err(MarketplaceError::RequestNotLocked),
+        RequestLifecycle::RequestLocked => None,
+        RequestLifecycle::PartiallyPaid => Some(PAYMENT_INFO.load(deps.storage, request_id)?),
+        _ => return Err(MarketplaceError::RequestNotAccepted),
+    };
+
+    let offer = OFFERS.load(deps.storage, request.accepted_offer_id)?;
+    if !offer.is_accepted {
+        return Err(MarketplaceError::RequestNotAccepted);
+    }
+    // The seller may have unbonded since making the offer; escrow only opens
+    // while `min_stake` is there to be slashed.
+    if payment_info.is_none() {
+        ensure_staked(deps, request.locked_seller_id)?;
+    }
+
+    if let Some(payment_info) = &payment_info {
+        if payment_info.coin != *coin {
+            return Err(MarketplaceError::PaymentCoinMismatch);
+        }
+    }
+
+    let price = price_due(&offer, payment_info.as_ref());
+    Ok((request, offer, payment_info, price))
+}
+
+/// The part of the offer price due now, in `PRICE_UNIT`: the full price, the
+/// deposit or the next installment.
+fn price_due(offer: &Offer, payment_info: Option<&PaymentInfo>) -> Uint128 {
+    let price = match (&offer.payment_schedule, payment_info) {
+        (None, _) => offer.accepted_price(),
+        (Some(schedule), None) => schedule.deposit,
+        (Some(schedule), Some(payment_info)) => {
+            schedule.installments[payment_info.installments_paid as usize].amount
+        }
+    };
+    Uint128::from(price)
+}
+
+/// Converts `price` into `coin` and enforces the buyer's slippage bound.
+fn payment_amount(
+    deps: Deps,
+    config: &Config,
+    coin: &CoinPayment,
+    price: Uint128,
+    max_amount: Option<Uint128>,
+) -> Result<Uint128, MarketplaceError> {
+    let amount = oracle::convert(&deps.querier, config, coin, price)?;
+    if let Some(max_amount) = max_amount {
+        if amount > max_amount {
+            return Err(MarketplaceError::SlippageExceeded { amount, max_amount });
+        }
+    }
+    Ok(amount)
+}
+
+/// Adds `amount` (worth `price`) to the request's payment record and moves the
+/// request to `Paid` once the offer price is covered, `PartiallyPaid` otherwise.
+#[allow(clippy::too_many_arguments)]
+fn record_payment(
+    storage: &mut dyn Storage,
+    env: &Env,
+    info: &MessageInfo,
+    mut request: Request,
+    offer: &Offer,
+    payment_info: Option<PaymentInfo>,
+    coin: CoinPayment,
+    price: Uint128,
+    amount: Uint128,
+    delivery_code: Option<HexBinary>,
+) -> Result<Event, MarketplaceError> {
+    if delivery_code.as_ref().is_some_and(|code| code.len() != 32) {
+        return Err(MarketplaceError::InvalidCommitment);
+    }
+    let now = env.block.time.seconds();
+    let mut payment_info = match payment_info {
+        Some(mut payment_info) => {
+            payment_info.installments_paid += 1;
+            payment_info
+        }
+        None => {
+            OPEN_ESCROWS.update(storage, request.locked_seller_id, |open| -> StdResult<_> {
+                Ok(open.unwrap_or_default() + 1)
+            })?;
+            PaymentInfo {
+                buyer: info.sender.clone(),
+                request_id: request.id,
+                seller: offer.authority.clone(),
+                authority: info.sender.clone(),
+                amount: Uint128::zero(),
+                coin,
+                created_at: now,
+                updated_at: now,
+                installments_paid: 0,
+                next_due_at: None,
+                price_paid: Uint128::zero(),
+                deposit: amount,
+                delivery_code: None,
+            }
+        }
     };
+    if delivery_code.is_some() {
+        payment_info.delivery_code = delivery_code;
+    }
 
-    if coin == CoinPayment::Cosmos {
-        // Check if the correct amount of native tokens was sent
-        let amount_sent = info
-            .funds
-            .iter()
-            .find(|c| c.denom == COIN_DENOM)
-            .map(|c| c.amount)
-            .unwrap_or_default();
-        if amount_sent < Uint128::from(offer.price) {
-            return Err(MarketplaceError::InsufficientFunds);
-        }
-
-        new_payment_info.amount = Uint128::from(offer.price);
-        PAYMENT_INFO.save(deps.storage, request_id, &new_payment_info)?;
-
-        Ok(Response::new().add_event(
-            cosmwasm_std::Event::new("request_payment_transacted")
-                .add_attribute("amount", new_payment_info.amount.to_string())
-                .add_attribute("coin", format!("{:?}", coin))
-                .add_attribute("request_id", request_id.to_string())
-                .add_attribute("authority", offer.authority.to_string())
-                .add_attribute("buyer", info.sender.to_string()),
-        ))
+    payment_info.amount += amount;
+    payment_info.price_paid += price;
+    payment_info.updated_at = now;
+    payment_info.next_due_at = offer.payment_schedule.as_ref().and_then(|schedule| {
+        schedule
+            .installments
+            .get(payment_info.installments_paid as usize)
+            .map(|installment| payment_info.created_at + installment.due_after)
+    });
+
+    let event = if payment_info.price_paid >= Uint128::from(offer.accepted_price()) {
+        request.paid = true;
+        request.lifecycle = RequestLifecycle::Paid;
+        events::request_paid(&payment_info)
     } else {
-        Err(MarketplaceError::UnknownPaymentType)
+        request.lifecycle = RequestLifecycle::PartiallyPaid;
+        events::installment_paid(&payment_info, amount)
+    };
+    request.updated_at = now;
+
+    PAYMENT_INFO.save(storage, request.id, &payment_info)?;
+    REQUESTS.save(storage, request.id, &request)?;
+
+    Ok(event)
+}
+
+pub fn pay_for_request_token(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    request_id: u64,
+    coin: CoinPayment,
+    max_amount: Option<Uint128>,
+    delivery_code: Option<HexBinary>,
+) -> Result<Response, MarketplaceError> {
+    if coin != CoinPayment::USDT {
+        return Err(MarketplaceError::UnknownPaymentType);
+    }
+    // The token is pulled with an allowance, so native funds have no place here.
+    if let Some(stray) = info.funds.first() {
+        return Err(MarketplaceError::UnexpectedFunds {
+            denom: stray.denom.clone(),
+        });
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let (request, offer, payment_info, price) =
+        load_payable_request(deps.as_ref(), &env, &info, request_id, &coin)?;
+    let amount_due = payment_amount(deps.as_ref(), &config, &coin, price, max_amount)?;
+
+    let transfer_msg = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: config.usdt_addr.to_string(),
+        msg: to_json_binary(&Cw20ExecuteMsg::TransferFrom {
+            owner: info.sender.to_string(),
+            recipient: env.contract.address.to_string(),
+            amount: amount_due,
+        })?,
+        funds: vec![],
+    });
+
+    let event = record_payment(
+        deps.storage,
+        &env,
+        &info,
+        request,
+        &offer,
+        payment_info,
+        coin,
+        price,
+        amount_due,
+        delivery_code,
+    )?;
+
+    Ok(Response::new()
+        .add_message(transfer_msg)
+        .add_attribute("method", "pay_for_request_token")
+        .add_event(event))
+}
+
+pub fn pay_for_request(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    request_id: u64,
+    coin: CoinPayment,
+    max_amount: Option<Uint128>,
+    delivery_code: Option<HexBinary>,
+) -> Result<Response, MarketplaceError> {
+    if coin != CoinPayment::Cosmos {
+        return Err(MarketplaceError::UnknownPaymentType);
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let (request, offer, payment_info, price) =
+        load_payable_request(deps.as_ref(), &env, &info, request_id, &coin)?;
+    let amount_due = payment_amount(deps.as_ref(), &config, &coin, price, max_amount)?;
+
+    // Only the payment denom is accepted, anything else would be stranded
+    if let Some(stray) = info.funds.iter().find(|c| c.denom != COIN_DENOM) {
+        return Err(MarketplaceError::UnexpectedFunds {
+            denom: stray.denom.clone(),
+        });
+    }
+
+    // Check if the correct amount of native tokens was sent
+    let amount_sent: Uint128 = info.funds.iter().map(|c| c.amount).sum();
+    if amount_sent < amount_due {
+        return Err(MarketplaceError::InsufficientFunds);
+    }
+    let excess = amount_sent - amount_due;
+
+    let event = record_payment(
+        deps.storage,
+        &env,
+        &info,
+        request,
+        &offer,
+        payment_info,
+        coin,
+        price,
+        amount_due,
+        delivery_code,
+    )?;
+
+    let mut response = Response::new()
+        .add_attribute("method", "pay_for_request")
+        .add_event(event);
+
+    // Send any overpayment straight back to the buyer
+    if !excess.is_zero() {
+        response = response
+            .add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: vec![Coin {
+                    denom: COIN_DENOM.to_string(),
+                    amount: excess,
+                }],
+            })
+            .add_event(events::overpayment_refunded(
+                request_id,
+                &info.sender,
+                excess,
+            ));
     }
+
+    Ok(response)
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetUser { address } => to_json_binary(&query_user(deps, address)?),
-        QueryMsg::GetRequest { request_id } => to_json_binary(&query_request(deps, request_id)?),
-        QueryMsg::GetAllRequests {} => to_json_binary(&query_all_requests(deps)?),
+        QueryMsg::GetRequest { request_id } => {
+            to_json_binary(&query_request(deps, env, request_id)?)
+        }
+        QueryMsg::GetAllRequests {} => to_json_binary(&query_all_requests(deps, env)?),
+        QueryMsg::GetRequestsByBudget {
+            min_price,
+            max_price,
+            coin,
+        } => to_json_binary(&query_requests_by_budget(
+            deps, env, min_price, max_price, coin,
+        )?),
         QueryMsg::GetOffer { offer_id } => to_json_binary(&query_offer(deps, offer_id)?),
         QueryMsg::GetOffersByRequest { request_id } => {
-            to_json_binary(&query_offers_by_request(deps, request_id)?)
+            to_json_binary(&query_offers_by_request(deps, env, request_id)?)
         }
 
         QueryMsg::GetLocationPreference { address } => {
-            let user = USERS.load(deps.storage, deps.api.addr_validate(&address)?.as_bytes())?;
-            to_json_binary(&user.location_enabled)
+            to_json_binary(&get_location_preference(deps, address)?)
         }
 
         QueryMsg::GetUserStores { address } => to_json_binary(&get_user_stores(deps, address)?),
 
-        QueryMsg::GetUserRequests { address } => to_json_binary(&get_user_requests(deps, address)?),
+        QueryMsg::GetUserRequests { address } => {
+            to_json_binary(&get_user_requests(deps, env, address)?)
+        }
         QueryMsg::GetUserPaymentHistory { address } => {
             to_json_binary(&get_user_payment_history(deps, address)?)
         }
 
-        QueryMsg::GetSellerOffers { address } => to_json_binary(&get_seller_offers(deps, address)?),
+        QueryMsg::GetSellerOffers { address } => {
+            to_json_binary(&get_seller_offers(deps, env, address)?)
+        }
 
         QueryMsg::GetUserById { user_id } => to_json_binary(&get_user_by_id(deps, user_id)?),
+
+        QueryMsg::GetConfig {} => to_json_binary(&query_config(deps)?),
+        QueryMsg::GetSellerStake { address } => to_json_binary(&query_seller_stake(deps, address)?),
+        QueryMsg::GetDeliveryStatus { request_id } => {
+            to_json_binary(&query_delivery_status(deps, request_id)?)
+        }
+        QueryMsg::GetModerators {} => to_json_binary(&query_moderators(deps)?),
+        QueryMsg::PauseStatus {} => to_json_binary(&query_pause_status(deps)?),
+        QueryMsg::GetPaymentQuote { request_id, coin } => {
+            to_json_binary(&query_payment_quote(deps, request_id, coin)?)
+        }
     }
 }
 
-pub fn query_user(deps: Deps, address: String) -> StdResult<User> {
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(ConfigResponse { config })
+}
+
+/// What `PayForRequest` would charge right now in `coin`.
+pub fn query_payment_quote(
+    deps: Deps,
+    request_id: u64,
+    coin: CoinPayment,
+) -> StdResult<PaymentQuoteResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let request = REQUESTS.load(deps.storage, request_id)?;
+    let payment_info = match request.lifecycle {
+        RequestLifecycle::AcceptedByBuyer | RequestLifecycle::RequestLocked => None,
+        RequestLifecycle::PartiallyPaid => Some(PAYMENT_INFO.load(deps.storage, request_id)?),
+        _ => return Err(StdError::generic_err("Request is not awaiting payment")),
+    };
+    let offer = OFFERS.load(deps.storage, request.accepted_offer_id)?;
+
+    let price = price_due(&offer, payment_info.as_ref());
+    let amount = oracle::convert(&deps.querier, &config, &coin, price)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    Ok(PaymentQuoteResponse { price, amount })
+}
+
+pub fn query_pause_status(deps: Deps) -> StdResult<PauseStatusResponse> {
+    let paused = PAUSED.may_load(deps.storage)?.unwrap_or_default();
+    Ok(PauseStatusResponse { paused })
+}
+
+pub fn query_moderators(deps: Deps) -> StdResult<ModeratorsResponse> {
+    let moderators = MODERATORS
+        .keys(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+        .collect::<StdResult<Vec<Addr>>>()?;
+    Ok(ModeratorsResponse { moderators })
+}
+
+pub fn query_delivery_status(deps: Deps, request_id: u64) -> StdResult<DeliveryStatusResponse> {
+    let request = REQUESTS.load(deps.storage, request_id)?;
+    let config = CONFIG.load(deps.storage)?;
+    let auto_release_at = match request.lifecycle {
+        RequestLifecycle::Paid => request.auto_release_at(config.auto_release_after),
+        _ => None,
+    };
+    Ok(DeliveryStatusResponse {
+        milestones: request.delivery,
+        auto_release_at,
+    })
+}
+
+pub fn query_seller_stake(deps: Deps, address: String) -> StdResult<SellerStakeResponse> {
+    let addr = deps.api.addr_validate(&address)?;
+    let user = USERS.load(deps.storage, addr.as_bytes())?;
+    let stake = SELLER_STAKES
+        .may_load(deps.storage, user.id)?
+        .unwrap_or_default();
+    Ok(SellerStakeResponse { stake })
+}
+
+pub fn query_user(deps: Deps, address: String) -> StdResult<UserResponse> {
     let addr = deps.api.addr_validate(&address)?;
     let user = USERS.load(deps.storage, addr.as_bytes())?;
-    Ok(user)
+    user_response(deps, user)
 }
 
-pub fn get_user_by_id(deps: Deps, user_id: u64) -> StdResult<User> {
+pub fn get_user_by_id(deps: Deps, user_id: u64) -> StdResult<UserResponse> {
     let user = USERS_BY_ID.load(deps.storage, user_id)?;
-    Ok(user)
+    user_response(deps, user)
+}
+
+fn user_response(deps: Deps, user: User) -> StdResult<UserResponse> {
+    let completed_sales = SELLER_SALES
+        .may_load(deps.storage, user.id)?
+        .unwrap_or_default();
+    let suspended = SUSPENDED_USERS.has(deps.storage, user.id);
+    Ok(UserResponse {
+        user,
+        completed_sales,
+        suspended,
+    })
+}
+
+pub fn get_location_preference(
+    deps: Deps,
+    address: String,
+) -> StdResult<LocationPreferenceResponse> {
+    let addr = deps.api.addr_validate(&address)?;
+    let user = USERS.load(deps.storage, addr.as_bytes())?;
+    Ok(LocationPreferenceResponse {
+        enabled: user.location_enabled,
+    })
 }
 
-pub fn get_user_stores(deps: Deps, address: String) -> StdResult<Vec<Store>> {
+pub fn get_user_stores(deps: Deps, address: String) -> StdResult<StoresResponse> {
     let addr = deps.api.addr_validate(&address)?;
     let stores = USER_STORE_IDS.may_load(deps.storage, addr.as_bytes());
 
     if stores.is_err() {
-        return Ok(vec![]);
+        return Ok(StoresResponse { stores: vec![] });
     }
 
     let stored_id = stores.unwrap();
 
     if stored_id.is_none() {
-        return Ok(vec![]);
+        return Ok(StoresResponse { stores: vec![] });
     }
 
     let store_ids = stored_id.unwrap();
@@ -657,10 +2669,11 @@ pub fn get_user_stores(deps: Deps, address: String) -> StdResult<Vec<Store>> {
         .map(|store_id| STORES.load(deps.storage, *store_id))
         .collect::<StdResult<Vec<Store>>>()?;
 
-    Ok(stores)
+    Ok(StoresResponse { stores })
 }
 
-pub fn get_seller_offers(deps: Deps, address: String) -> StdResult<Vec<Offer>> {
+pub fn get_seller_offers(deps: Deps, env: Env, address: String) -> StdResult<OffersResponse> {
+    let now = env.block.time.seconds();
     let addr = deps.api.addr_validate(&address)?;
     let user = USERS.load(deps.storage, addr.as_bytes())?;
 
@@ -670,7 +2683,7 @@ pub fn get_seller_offers(deps: Deps, address: String) -> StdResult<Vec<Offer>> {
             match item {
                 Ok((_, offer)) => {
                     // Filter based on the user's ID
-                    if offer.seller_id == user.id {
+                    if offer.seller_id == user.id && is_listed_offer(&offer, now) {
                         Some(Ok(offer))
                     } else {
                         None
@@ -682,42 +2695,97 @@ pub fn get_seller_offers(deps: Deps, address: String) -> StdResult<Vec<Offer>> {
         })
         .collect::<StdResult<Vec<Offer>>>()?;
 
-    Ok(offers)
+    Ok(OffersResponse { offers })
 }
 
-pub fn query_request(deps: Deps, request_id: u64) -> StdResult<Request> {
-    let request = REQUESTS.load(deps.storage, request_id)?;
-    Ok(request)
+/// List queries leave out requests that are expired, cancelled or hidden.
+fn is_listed_request(request: &Request, now: u64) -> bool {
+    !request.is_expired(now) && request.lifecycle != RequestLifecycle::Cancelled && !request.hidden
+}
+
+/// List queries leave out offers that are expired, cancelled or hidden.
+fn is_listed_offer(offer: &Offer, now: u64) -> bool {
+    !offer.is_expired(now) && !offer.is_cancelled && !offer.hidden
+}
+
+pub fn query_request(deps: Deps, env: Env, request_id: u64) -> StdResult<RequestResponse> {
+    let mut request = REQUESTS.load(deps.storage, request_id)?;
+    request.lifecycle = request.lifecycle_at(env.block.time.seconds());
+    Ok(RequestResponse {
+        locked_until: request.locked_until(),
+        request,
+    })
 }
 
-pub fn query_all_requests(deps: Deps) -> StdResult<Vec<Request>> {
+pub fn query_all_requests(deps: Deps, env: Env) -> StdResult<RequestsResponse> {
+    let now = env.block.time.seconds();
     let requests: Vec<Request> = REQUESTS
         .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
-        .map(|item| {
-            let (_, request) = item?;
-            Ok(request)
+        .filter_map(|item| match item {
+            Ok((_, request)) if !is_listed_request(&request, now) => None,
+            Ok((_, mut request)) => {
+                request.lifecycle = request.lifecycle_at(now);
+                Some(Ok(request))
+            }
+            Err(e) => Some(Err(e)),
+        })
+        .collect::<StdResult<Vec<Request>>>()?;
+
+    Ok(RequestsResponse { requests })
+}
+
+pub fn query_requests_by_budget(
+    deps: Deps,
+    env: Env,
+    min_price: Option<u128>,
+    max_price: Option<u128>,
+    coin: Option<CoinPayment>,
+) -> StdResult<RequestsResponse> {
+    let now = env.block.time.seconds();
+    let in_range = |budget: &Budget| {
+        budget.coin == coin
+            && min_price.is_none_or(|min| budget.max_price >= min)
+            && max_price.is_none_or(|max| budget.max_price <= max)
+    };
+    let requests: Vec<Request> = REQUESTS
+        .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+        .filter_map(|item| match item {
+            Ok((_, request))
+                if matches!(
+                    request.lifecycle,
+                    RequestLifecycle::Pending | RequestLifecycle::AcceptedBySeller
+                ) && is_listed_request(&request, now)
+                    && request.budget.as_ref().is_some_and(in_range) =>
+            {
+                Some(Ok(request))
+            }
+            Ok(_) => None,
+            Err(e) => Some(Err(e)),
         })
         .collect::<StdResult<Vec<Request>>>()?;
 
-    Ok(requests)
+    Ok(RequestsResponse { requests })
 }
 
-pub fn query_offer(deps: Deps, offer_id: u64) -> StdResult<Offer> {
+pub fn query_offer(deps: Deps, offer_id: u64) -> StdResult<OfferResponse> {
     let offer = OFFERS.load(deps.storage, offer_id)?;
-    Ok(offer)
+    Ok(OfferResponse { offer })
 }
 
-pub fn query_offers_by_request(deps: Deps, request_id: u64) -> StdResult<Vec<Offer>> {
+pub fn query_offers_by_request(deps: Deps, env: Env, request_id: u64) -> StdResult<OffersResponse> {
+    let now = env.block.time.seconds();
     let request = REQUESTS.load(deps.storage, request_id)?;
     let offers: Vec<Offer> = request
         .offer_ids
         .iter()
         .map(|offer_id| OFFERS.load(deps.storage, *offer_id))
+        .filter(|offer| !matches!(offer, Ok(offer) if !is_listed_offer(offer, now)))
         .collect::<StdResult<Vec<Offer>>>()?;
-    Ok(offers)
+    Ok(OffersResponse { offers })
 }
 
-pub fn get_user_requests(deps: Deps, address: String) -> StdResult<Vec<Request>> {
+pub fn get_user_requests(deps: Deps, env: Env, address: String) -> StdResult<RequestsResponse> {
+    let now = env.block.time.seconds();
     let addr: cosmwasm_std::Addr = deps.api.addr_validate(&address)?;
     let user = USERS.load(deps.storage, addr.as_bytes())?;
 
@@ -725,9 +2793,10 @@ pub fn get_user_requests(deps: Deps, address: String) -> StdResult<Vec<Request>>
         .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
         .filter_map(|item| {
             match item {
-                Ok((_, request)) => {
+                Ok((_, mut request)) => {
                     // Filter based on the user's ID
-                    if request.buyer_id == user.id {
+                    if request.buyer_id == user.id && is_listed_request(&request, now) {
+                        request.lifecycle = request.lifecycle_at(now);
                         Some(Ok(request))
                     } else {
                         None
@@ -739,9 +2808,9 @@ pub fn get_user_requests(deps: Deps, address: String) -> StdResult<Vec<Request>>
         })
         .collect::<StdResult<Vec<Request>>>()?;
 
-    Ok(requests)
+    Ok(RequestsResponse { requests })
 }
-pub fn get_user_payment_history(deps: Deps, address: String) -> StdResult<Vec<PaymentInfo>> {
+pub fn get_user_payment_history(deps: Deps, address: String) -> StdResult<PaymentHistoryResponse> {
     let addr: cosmwasm_std::Addr = deps.api.addr_validate(&address)?;
     let user = USERS.load(deps.storage, addr.as_bytes())?;
 
@@ -762,76 +2831,377 @@ pub fn get_user_payment_history(deps: Deps, address: String) -> StdResult<Vec<Pa
         })
         .collect::<StdResult<Vec<PaymentInfo>>>()?;
 
-    Ok(payments)
-}
-
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-//     use cosmwasm_std::testing::{mock_dependencies_with_balance, mock_env, mock_info};
-//     use cosmwasm_std::{coins, from_binary};
-
-//     #[test]
-//     fn proper_initialization() {
-//         let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
-
-//         let msg = InstantiateMsg { count: 17 };
-//         let info = mock_info("creator", &coins(1000, "earth"));
-
-//         // we can just call .unwrap() to assert this was a success
-//         let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-//         assert_eq!(0, res.messages.len());
-
-//         // it worked, let's query the state
-//         let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCount ).unwrap();
-//         let value: CountResponse = from_binary(&res).unwrap();
-//         assert_eq!(17, value.count);
-//     }
-
-//     #[test]
-//     fn increment() {
-//         let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
-
-//         let msg = InstantiateMsg { count: 17 };
-//         let info = mock_info("creator", &coins(2, "token"));
-//         let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-//         // beneficiary can release it
-//         let info = mock_info("anyone", &coins(2, "token"));
-//         let msg = ExecuteMsg::Increment {};
-//         let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-//         // should increase counter by 1
-//         let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCount ).unwrap();
-//         let value: CountResponse = from_binary(&res).unwrap();
-//         assert_eq!(18, value.count);
-//     }
-
-//     #[test]
-//     fn reset() {
-//         let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
-
-//         let msg = InstantiateMsg { count: 17 };
-//         let info = mock_info("creator", &coins(2, "token"));
-//         let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-//         // beneficiary can release it
-//         let unauth_info = mock_info("anyone", &coins(2, "token"));
-//         let msg = ExecuteMsg::Reset { count: 5 };
-//         let res = execute(deps.as_mut(), mock_env(), unauth_info, msg);
-//         match res {
-//             Err(MarketplaceError::Unauthorized ) => {}
-//             _ => panic!("Must return unauthorized error"),
-//         }
-
-//         // only the original creator can reset the counter
-//         let auth_info = mock_info("creator", &coins(2, "token"));
-//         let msg = ExecuteMsg::Reset { count: 5 };
-//         let _res = execute(deps.as_mut(), mock_env(), auth_info, msg).unwrap();
-
-//         // should now be 5
-//         let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCount ).unwrap();
-//         let value: CountResponse = from_binary(&res).unwrap();
-//         assert_eq!(5, value.count);
-//     }
-// }
+    Ok(PaymentHistoryResponse { payments })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{
+        message_info, mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage,
+    };
+    use cosmwasm_std::{coins, Addr, OwnedDeps};
+
+    type MockDeps = OwnedDeps<MockStorage, MockApi, MockQuerier>;
+
+    fn setup() -> (MockDeps, Addr, Addr) {
+        let mut deps = mock_dependencies();
+        let buyer = deps.api.addr_make("buyer");
+        let seller = deps.api.addr_make("seller");
+        let creator = deps.api.addr_make("creator");
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&creator, &[]),
+            InstantiateMsg {
+                time_to_lock: None,
+                min_time_to_lock: None,
+                max_time_to_lock: None,
+                usdt_addr: None,
+                oracle: None,
+                min_stake: None,
+                unbonding_period: None,
+                max_open_requests: None,
+                max_offers_per_request: None,
+                max_sellers_per_request: None,
+                creation_fee: None,
+                auto_release_after: None,
+            },
+        )
+        .unwrap();
+        register(&mut deps, &buyer, AccountType::Buyer);
+        register(&mut deps, &seller, AccountType::Seller);
+        (deps, buyer, seller)
+    }
+
+    fn register(deps: &mut MockDeps, sender: &Addr, account_type: AccountType) {
+        let msg = ExecuteMsg::CreateUser {
+            username: "user".to_string(),
+            phone: "+2348000000000".to_string(),
+            latitude: 1,
+            longitude: 1,
+            account_type,
+        };
+        execute(deps.as_mut(), mock_env(), message_info(sender, &[]), msg).unwrap();
+    }
+
+    fn create_request(deps: &mut MockDeps, buyer: &Addr) -> u64 {
+        let request_id = REQUEST_COUNT.load(&deps.storage).unwrap();
+        let msg = ExecuteMsg::CreateRequest {
+            name: "Rice".to_string(),
+            description: "50kg bag".to_string(),
+            images: vec![],
+            latitude: 1,
+            longitude: 1,
+            expires_at: None,
+            time_to_lock: None,
+            line_items: vec![],
+            budget: None,
+            auction: None,
+            auto_accept: None,
+        };
+        execute(deps.as_mut(), mock_env(), message_info(buyer, &[]), msg).unwrap();
+        request_id
+    }
+
+    fn create_offer(deps: &mut MockDeps, seller: &Addr, request_id: u64, price: u128) -> u64 {
+        let offer_id = OFFER_COUNT.load(&deps.storage).unwrap();
+        let msg = ExecuteMsg::CreateOffer {
+            price,
+            images: vec![],
+            request_id,
+            store_name: "Store".to_string(),
+            expires_at: None,
+            payment_schedule: None,
+            line_quotes: vec![],
+        };
+        execute(deps.as_mut(), mock_env(), message_info(seller, &[]), msg).unwrap();
+        offer_id
+    }
+
+    fn second_seller(deps: &mut MockDeps) -> Addr {
+        let seller = deps.api.addr_make("second_seller");
+        register(deps, &seller, AccountType::Seller);
+        seller
+    }
+
+    fn accept(
+        deps: &mut MockDeps,
+        env: Env,
+        sender: &Addr,
+        offer_id: u64,
+    ) -> Result<Response, MarketplaceError> {
+        let msg = ExecuteMsg::AcceptOffer {
+            offer_id,
+            lines: None,
+        };
+        execute(deps.as_mut(), env, message_info(sender, &[]), msg)
+    }
+
+    fn env_after(seconds: u64) -> Env {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(seconds);
+        env
+    }
+
+    #[test]
+    fn accept_offer_records_accepted_offer_id() {
+        let (mut deps, buyer, seller) = setup();
+        let request_id = create_request(&mut deps, &buyer);
+        let offer_id = create_offer(&mut deps, &seller, request_id, 100);
+
+        accept(&mut deps, mock_env(), &buyer, offer_id).unwrap();
+
+        let request = REQUESTS.load(&deps.storage, request_id).unwrap();
+        assert_eq!(request.accepted_offer_id, offer_id);
+        assert_eq!(request.lifecycle, RequestLifecycle::AcceptedByBuyer);
+        assert_eq!(request.seller_price_quote, 100);
+        assert!(OFFERS.load(&deps.storage, offer_id).unwrap().is_accepted);
+    }
+
+    #[test]
+    fn accept_offer_rejects_other_buyers() {
+        let (mut deps, buyer, seller) = setup();
+        let other_buyer = deps.api.addr_make("other_buyer");
+        register(&mut deps, &other_buyer, AccountType::Buyer);
+        let request_id = create_request(&mut deps, &buyer);
+        let offer_id = create_offer(&mut deps, &seller, request_id, 100);
+
+        let err = accept(&mut deps, mock_env(), &other_buyer, offer_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::UnauthorizedBuyer));
+    }
+
+    #[test]
+    fn accept_offer_switches_offer_within_lock_window() {
+        let (mut deps, buyer, seller) = setup();
+        let other = second_seller(&mut deps);
+        let request_id = create_request(&mut deps, &buyer);
+        let first = create_offer(&mut deps, &seller, request_id, 100);
+        let second = create_offer(&mut deps, &other, request_id, 80);
+
+        accept(&mut deps, mock_env(), &buyer, first).unwrap();
+        accept(&mut deps, env_after(TIME_TO_LOCK - 1), &buyer, second).unwrap();
+
+        let request = REQUESTS.load(&deps.storage, request_id).unwrap();
+        assert_eq!(request.accepted_offer_id, second);
+        assert_eq!(request.seller_price_quote, 80);
+        assert!(!OFFERS.load(&deps.storage, first).unwrap().is_accepted);
+        assert!(OFFERS.load(&deps.storage, second).unwrap().is_accepted);
+    }
+
+    #[test]
+    fn accept_offer_rejects_after_lock_window() {
+        let (mut deps, buyer, seller) = setup();
+        let other = second_seller(&mut deps);
+        let request_id = create_request(&mut deps, &buyer);
+        let first = create_offer(&mut deps, &seller, request_id, 100);
+        let second = create_offer(&mut deps, &other, request_id, 80);
+
+        accept(&mut deps, mock_env(), &buyer, first).unwrap();
+        let res = query_request(deps.as_ref(), env_after(TIME_TO_LOCK - 1), request_id).unwrap();
+        assert_eq!(res.request.lifecycle, RequestLifecycle::AcceptedByBuyer);
+
+        let err = accept(&mut deps, env_after(TIME_TO_LOCK), &buyer, second).unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestLocked));
+        let res = query_request(deps.as_ref(), env_after(TIME_TO_LOCK), request_id).unwrap();
+        assert_eq!(res.request.lifecycle, RequestLifecycle::RequestLocked);
+        assert_eq!(
+            res.locked_until,
+            Some(mock_env().block.time.seconds() + TIME_TO_LOCK)
+        );
+        // Only reported that way: storage keeps the accepted state.
+        let request = REQUESTS.load(&deps.storage, request_id).unwrap();
+        assert_eq!(request.lifecycle, RequestLifecycle::AcceptedByBuyer);
+    }
+
+    #[test]
+    fn new_offer_does_not_reset_accepted_request() {
+        let (mut deps, buyer, seller) = setup();
+        let other = second_seller(&mut deps);
+        let request_id = create_request(&mut deps, &buyer);
+        let offer_id = create_offer(&mut deps, &seller, request_id, 100);
+        accept(&mut deps, mock_env(), &buyer, offer_id).unwrap();
+
+        create_offer(&mut deps, &other, request_id, 90);
+
+        let request = REQUESTS.load(&deps.storage, request_id).unwrap();
+        assert_eq!(request.lifecycle, RequestLifecycle::AcceptedByBuyer);
+    }
+
+    #[test]
+    fn pay_for_request_charges_accepted_offer() {
+        let (mut deps, buyer, seller) = setup();
+        let other = second_seller(&mut deps);
+        let request_id = create_request(&mut deps, &buyer);
+        create_offer(&mut deps, &other, request_id, 100);
+        let offer_id = create_offer(&mut deps, &seller, request_id, 80);
+        accept(&mut deps, mock_env(), &buyer, offer_id).unwrap();
+
+        let msg = ExecuteMsg::PayForRequest {
+            request_id,
+            coin: CoinPayment::Cosmos,
+            max_amount: None,
+            delivery_code: None,
+        };
+        let info = message_info(&buyer, &coins(80, COIN_DENOM));
+        execute(deps.as_mut(), env_after(TIME_TO_LOCK), info, msg).unwrap();
+
+        let request = REQUESTS.load(&deps.storage, request_id).unwrap();
+        assert!(request.paid);
+        assert_eq!(request.lifecycle, RequestLifecycle::Paid);
+        let payment = PAYMENT_INFO.load(&deps.storage, request_id).unwrap();
+        assert_eq!(payment.amount, Uint128::new(80));
+        assert_eq!(payment.seller, seller);
+    }
+
+    #[test]
+    fn update_config_requires_admin() {
+        let (mut deps, buyer, _) = setup();
+        let msg = ExecuteMsg::UpdateConfig {
+            time_to_lock: Some(3600),
+            min_time_to_lock: None,
+            max_time_to_lock: None,
+            oracle: None,
+            min_stake: None,
+            unbonding_period: None,
+            max_open_requests: None,
+            max_offers_per_request: None,
+            max_sellers_per_request: None,
+            creation_fee: None,
+            auto_release_after: None,
+        };
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&buyer, &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, MarketplaceError::Unauthorized));
+
+        let admin = deps.api.addr_make("creator");
+        execute(deps.as_mut(), mock_env(), message_info(&admin, &[]), msg).unwrap();
+        assert_eq!(CONFIG.load(&deps.storage).unwrap().time_to_lock, 3600);
+    }
+
+    #[test]
+    fn create_request_applies_lock_window_override() {
+        let (mut deps, buyer, seller) = setup();
+        let mut msg = ExecuteMsg::CreateRequest {
+            name: "Rice".to_string(),
+            description: "50kg bag".to_string(),
+            images: vec![],
+            latitude: 1,
+            longitude: 1,
+            expires_at: None,
+            time_to_lock: Some(MAX_TIME_TO_LOCK + 1),
+            line_items: vec![],
+            budget: None,
+            auction: None,
+            auto_accept: None,
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&buyer, &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, MarketplaceError::InvalidLockWindow));
+
+        if let ExecuteMsg::CreateRequest { time_to_lock, .. } = &mut msg {
+            *time_to_lock = Some(3600);
+        }
+        let request_id = REQUEST_COUNT.load(&deps.storage).unwrap();
+        execute(deps.as_mut(), mock_env(), message_info(&buyer, &[]), msg).unwrap();
+        let offer_id = create_offer(&mut deps, &seller, request_id, 100);
+        accept(&mut deps, mock_env(), &buyer, offer_id).unwrap();
+
+        let res = query_request(deps.as_ref(), mock_env(), request_id).unwrap();
+        assert_eq!(
+            res.locked_until,
+            Some(mock_env().block.time.seconds() + 3600)
+        );
+    }
+
+    #[test]
+    fn delete_request_removes_offers_and_notifies_sellers() {
+        let (mut deps, buyer, seller) = setup();
+        let other_seller = deps.api.addr_make("other_seller");
+        register(&mut deps, &other_seller, AccountType::Seller);
+        let request_id = create_request(&mut deps, &buyer);
+        // The seller's first offer lapses before they make another one.
+        let first = OFFER_COUNT.load(&deps.storage).unwrap();
+        let msg = ExecuteMsg::CreateOffer {
+            price: 100,
+            images: vec![],
+            request_id,
+            store_name: "Store".to_string(),
+            expires_at: Some(mock_env().block.time.seconds() + 10),
+            payment_schedule: None,
+            line_quotes: vec![],
+        };
+        execute(deps.as_mut(), mock_env(), message_info(&seller, &[]), msg).unwrap();
+        let second = OFFER_COUNT.load(&deps.storage).unwrap();
+        let msg = ExecuteMsg::CreateOffer {
+            price: 90,
+            images: vec![],
+            request_id,
+            store_name: "Store".to_string(),
+            expires_at: None,
+            payment_schedule: None,
+            line_quotes: vec![],
+        };
+        execute(
+            deps.as_mut(),
+            env_after(10),
+            message_info(&seller, &[]),
+            msg,
+        )
+        .unwrap();
+        let third = create_offer(&mut deps, &other_seller, request_id, 95);
+
+        let msg = ExecuteMsg::DeleteRequest { request_id };
+        let res = execute(deps.as_mut(), mock_env(), message_info(&buyer, &[]), msg).unwrap();
+
+        assert!(!REQUESTS.has(&deps.storage, request_id));
+        for offer_id in [first, second, third] {
+            assert!(!OFFERS.has(&deps.storage, offer_id));
+        }
+        assert_eq!(res.events.len(), 3);
+        assert_eq!(res.events[0].ty, "marketplace/request_deleted");
+        assert_eq!(res.events[1].ty, "marketplace/offers_cancelled");
+        assert_eq!(
+            res.events[1].attributes[2].value,
+            format!("{},{}", first, second)
+        );
+        assert!(
+            get_seller_offers(deps.as_ref(), mock_env(), seller.to_string())
+                .unwrap()
+                .offers
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn cancel_request_keeps_history() {
+        let (mut deps, buyer, seller) = setup();
+        let request_id = create_request(&mut deps, &buyer);
+        let offer_id = create_offer(&mut deps, &seller, request_id, 100);
+
+        let msg = ExecuteMsg::CancelRequest { request_id };
+        execute(deps.as_mut(), mock_env(), message_info(&buyer, &[]), msg).unwrap();
+
+        let request = REQUESTS.load(&deps.storage, request_id).unwrap();
+        assert_eq!(request.lifecycle, RequestLifecycle::Cancelled);
+        assert!(OFFERS.load(&deps.storage, offer_id).unwrap().is_cancelled);
+        assert!(query_all_requests(deps.as_ref(), mock_env())
+            .unwrap()
+            .requests
+            .is_empty());
+
+        let err = accept(&mut deps, mock_env(), &buyer, offer_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestCancelled));
+    }
+}
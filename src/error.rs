@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -21,7 +21,7 @@ pub enum MarketplaceError {
     OfferAlreadyAccepted,
     #[error("Request locked.")]
     RequestLocked,
-    #[error("Incorrect number of sellers.")]
+    #[error("Request has reached its maximum number of sellers.")]
     IncorrectNumberOfSellers,
     #[error("Request not accepted.")]
     RequestNotAccepted,
@@ -35,4 +35,133 @@ pub enum MarketplaceError {
     UnknownPaymentType,
     #[error("Insufficient funds")]
     InsufficientFunds,
+    #[error("Unexpected funds in {denom}.")]
+    UnexpectedFunds { denom: String },
+    #[error("Too many line items, at most {max_line_items} allowed.")]
+    TooManyLineItems { max_line_items: usize },
+    #[error("Quantity must be greater than zero.")]
+    ZeroQuantity,
+    #[error("Invalid quote for line {line}.")]
+    InvalidLineQuote { line: u32 },
+    #[error("Price does not match the quoted lines.")]
+    PriceMismatch,
+    #[error("Line {line} is not quoted in this offer.")]
+    LineNotQuoted { line: u32 },
+    #[error("Offers with a payment schedule must be accepted in full.")]
+    PartialAcceptanceWithSchedule,
+    #[error("Offer exceeds the buyer's budget.")]
+    OfferAboveBudget,
+    #[error("Auction windows must be in the future and in order.")]
+    InvalidAuctionWindow,
+    #[error("Request is not an auction.")]
+    NotAnAuction,
+    #[error("This request only accepts sealed bids.")]
+    SealedBidsOnly,
+    #[error("Bidding has closed.")]
+    BiddingClosed,
+    #[error("Not within the reveal window.")]
+    NotInRevealWindow,
+    #[error("Commitment must be a sha256 hash.")]
+    InvalidCommitment,
+    #[error("Revealed price does not match the commitment.")]
+    CommitmentMismatch,
+    #[error("Offer is not a sealed bid.")]
+    OfferNotSealed,
+    #[error("Offer has not been revealed.")]
+    OfferNotRevealed,
+    #[error("Auction has not ended yet.")]
+    AuctionNotEnded,
+    #[error("This auction selects its winner automatically.")]
+    AutoSelectAuction,
+    #[error("The buyer selects this auction's winner.")]
+    ManualSelectAuction,
+    #[error("No offer was revealed.")]
+    NoRevealedOffers,
+    #[error("Offers with line quotes, a payment schedule or a sealed bid cannot be negotiated.")]
+    NotNegotiable,
+    #[error("Waiting for an answer to the last counter-offer.")]
+    CounterOfferPending,
+    #[error("No counter-offer awaits an answer.")]
+    NoPendingCounterOffer,
+    #[error("Auto-accept deadline must be in the future and before the request expires.")]
+    InvalidAutoAccept,
+    #[error("Request does not auto-accept offers.")]
+    AutoAcceptNotEnabled,
+    #[error("Auto-accept deadline has not been reached.")]
+    DeadlineNotReached,
+    #[error("No offer can be accepted.")]
+    NoEligibleOffers,
+    #[error("Oracle returned an unusable price.")]
+    InvalidOraclePrice,
+    #[error("Payment of {amount} exceeds the maximum of {max_amount}.")]
+    SlippageExceeded {
+        amount: Uint128,
+        max_amount: Uint128,
+    },
+    #[error("Expiry must be in the future.")]
+    InvalidExpiry,
+    #[error("Request expired.")]
+    RequestExpired,
+    #[error("Offer expired.")]
+    OfferExpired,
+    #[error("Offer cancelled.")]
+    OfferCancelled,
+    #[error("Unauthorized.")]
+    Unauthorized,
+    #[error("Lock window out of bounds.")]
+    InvalidLockWindow,
+    #[error("Request cancelled.")]
+    RequestCancelled,
+    #[error("{field} must not be empty.")]
+    EmptyField { field: &'static str },
+    #[error("{field} must be at most {max_len} bytes.")]
+    FieldTooLong { field: &'static str, max_len: usize },
+    #[error("Invalid phone number.")]
+    InvalidPhone,
+    #[error("At most {max_images} images allowed.")]
+    TooManyImages { max_images: usize },
+    #[error("Invalid image URL.")]
+    InvalidImageUrl,
+    #[error("Price must be greater than zero.")]
+    ZeroPrice,
+    #[error("Invalid payment schedule.")]
+    InvalidPaymentSchedule,
+    #[error("Installments must be paid in the coin used for the deposit.")]
+    PaymentCoinMismatch,
+    #[error("Request not partially paid.")]
+    RequestNotPartiallyPaid,
+    #[error("No installment is overdue.")]
+    InstallmentNotOverdue,
+    #[error("Unauthorized seller.")]
+    UnauthorizedSeller,
+    #[error("At least {required} must be bonded.")]
+    InsufficientStake { required: Uint128 },
+    #[error("Stake is still unbonding.")]
+    StakeStillUnbonding,
+    #[error("At least {required} must stay bonded while escrow is open.")]
+    StakeLocked { required: Uint128 },
+    #[error("Nothing to withdraw.")]
+    NothingToWithdraw,
+    #[error("At most {max} open requests allowed.")]
+    TooManyOpenRequests { max: u32 },
+    #[error("Seller already has active offer {offer_id} on this request, update it instead.")]
+    ActiveOfferExists { offer_id: u64 },
+    #[error("At most {max} offers per request allowed.")]
+    TooManyOffers { max: u32 },
+    #[error("A creation fee of {fee} must be attached.")]
+    CreationFeeRequired { fee: Uint128 },
+    #[error("Delivery milestones must move forward.")]
+    InvalidMilestone,
+    #[error("Escrow cannot be released yet.")]
+    ReleaseNotDue,
+    #[error("Request has no escrowed payment.")]
+    RequestNotPaid,
+    #[error("Buyer did not set a delivery code.")]
+    DeliveryCodeNotSet,
+    #[error("Secret does not match the delivery code.")]
+    DeliveryCodeMismatch,
+    #[error("Account is suspended.")]
+    Suspended,
+    #[error("This operation is paused.")]
+    Paused,
 }
@@ -0,0 +1,341 @@
+use cosmwasm_std::{Addr, Event, Uint128};
+
+use crate::state::{
+    CoinPayment, Config, DeliveryMilestone, Offer, OfferRevision, PauseFlags, PaymentInfo, Request,
+    SellerStake, Store, User,
+};
+
+// Every state change emits at least one of the events below. The event types and
+// their attributes are documented in `schema/events.md`; treat them as part of
+// the public API and only ever add attributes.
+const EVENT_PREFIX: &str = "marketplace";
+
+fn event(name: &str) -> Event {
+    Event::new(format!("{}/{}", EVENT_PREFIX, name))
+}
+
+fn join<T: ToString>(values: &[T]) -> String {
+    let values: Vec<String> = values.iter().map(ToString::to_string).collect();
+    values.join(",")
+}
+
+pub fn config_updated(config: &Config) -> Event {
+    let mut event = event("config_updated")
+        .add_attribute("admin", config.admin.to_string())
+        .add_attribute("time_to_lock", config.time_to_lock.to_string())
+        .add_attribute("min_time_to_lock", config.min_time_to_lock.to_string())
+        .add_attribute("max_time_to_lock", config.max_time_to_lock.to_string())
+        .add_attribute("usdt_addr", config.usdt_addr.to_string());
+    if let Some(oracle) = &config.oracle {
+        event = event.add_attribute("oracle", oracle.to_string());
+    }
+    event
+        .add_attribute("min_stake", config.min_stake.to_string())
+        .add_attribute("unbonding_period", config.unbonding_period.to_string())
+        .add_attribute("max_open_requests", config.max_open_requests.to_string())
+        .add_attribute(
+            "max_offers_per_request",
+            config.max_offers_per_request.to_string(),
+        )
+        .add_attribute("creation_fee", config.creation_fee.to_string())
+        .add_attribute(
+            "max_sellers_per_request",
+            config.max_sellers_per_request.to_string(),
+        )
+        .add_attribute("auto_release_after", config.auto_release_after.to_string())
+}
+
+pub fn user_created(user: &User) -> Event {
+    event("user_created")
+        .add_attribute("user_id", user.id.to_string())
+        .add_attribute("address", user.authority.to_string())
+        .add_attribute("account_type", format!("{:?}", user.account_type))
+}
+
+pub fn user_updated(user: &User) -> Event {
+    event("user_updated")
+        .add_attribute("user_id", user.id.to_string())
+        .add_attribute("address", user.authority.to_string())
+        .add_attribute("account_type", format!("{:?}", user.account_type))
+}
+
+pub fn location_toggled(user: &User) -> Event {
+    event("location_toggled")
+        .add_attribute("user_id", user.id.to_string())
+        .add_attribute("address", user.authority.to_string())
+        .add_attribute("enabled", user.location_enabled.to_string())
+}
+
+pub fn pause_updated(paused: &PauseFlags) -> Event {
+    event("pause_updated")
+        .add_attribute("all", paused.all.to_string())
+        .add_attribute("payments", paused.payments.to_string())
+        .add_attribute("listings", paused.listings.to_string())
+}
+
+pub fn moderator_updated(address: &Addr, enabled: bool) -> Event {
+    event("moderator_updated")
+        .add_attribute("address", address.to_string())
+        .add_attribute("enabled", enabled.to_string())
+}
+
+pub fn user_suspended(user: &User, suspended: bool, moderator: &Addr) -> Event {
+    event("user_suspended")
+        .add_attribute("user_id", user.id.to_string())
+        .add_attribute("address", user.authority.to_string())
+        .add_attribute("suspended", suspended.to_string())
+        .add_attribute("moderator", moderator.to_string())
+}
+
+pub fn request_hidden(request: &Request, moderator: &Addr) -> Event {
+    event("request_hidden")
+        .add_attribute("request_id", request.id.to_string())
+        .add_attribute("hidden", request.hidden.to_string())
+        .add_attribute("moderator", moderator.to_string())
+}
+
+pub fn offer_hidden(offer: &Offer, moderator: &Addr) -> Event {
+    event("offer_hidden")
+        .add_attribute("offer_id", offer.id.to_string())
+        .add_attribute("request_id", offer.request_id.to_string())
+        .add_attribute("hidden", offer.hidden.to_string())
+        .add_attribute("moderator", moderator.to_string())
+}
+
+pub fn store_created(store: &Store, owner: &Addr) -> Event {
+    event("store_created")
+        .add_attribute("store_id", store.id.to_string())
+        .add_attribute("owner", owner.to_string())
+        .add_attribute("name", store.name.clone())
+}
+
+pub fn request_created(request: &Request, buyer: &Addr) -> Event {
+    let mut event = event("request_created")
+        .add_attribute("request_id", request.id.to_string())
+        .add_attribute("buyer", buyer.to_string())
+        .add_attribute("latitude", request.location.latitude.to_string())
+        .add_attribute("longitude", request.location.longitude.to_string())
+        .add_attribute("time_to_lock", request.time_to_lock.to_string())
+        .add_attribute("line_items", request.line_items.len().to_string());
+    if let Some(expires_at) = request.expires_at {
+        event = event.add_attribute("expires_at", expires_at.to_string());
+    }
+    if let Some(budget) = &request.budget {
+        event = event.add_attribute("max_price", budget.max_price.to_string());
+        if let Some(coin) = &budget.coin {
+            event = event.add_attribute("budget_coin", format!("{:?}", coin));
+        }
+    }
+    if !request.creation_fee.is_zero() {
+        event = event.add_attribute("creation_fee", request.creation_fee.to_string());
+    }
+    event
+}
+
+pub fn creation_fee_released(request: &Request, recipient: &Addr) -> Event {
+    event("creation_fee_released")
+        .add_attribute("request_id", request.id.to_string())
+        .add_attribute("recipient", recipient.to_string())
+        .add_attribute("amount", request.creation_fee.to_string())
+}
+
+pub fn request_deleted(request: &Request, buyer: &Addr) -> Event {
+    event("request_deleted")
+        .add_attribute("request_id", request.id.to_string())
+        .add_attribute("buyer", buyer.to_string())
+}
+
+pub fn request_cancelled(request: &Request, buyer: &Addr) -> Event {
+    event("request_cancelled")
+        .add_attribute("request_id", request.id.to_string())
+        .add_attribute("buyer", buyer.to_string())
+}
+
+pub fn offer_created(offer: &Offer) -> Event {
+    let mut event = event("offer_created")
+        .add_attribute("offer_id", offer.id.to_string())
+        .add_attribute("request_id", offer.request_id.to_string())
+        .add_attribute("price", offer.price.to_string())
+        .add_attribute("seller", offer.authority.to_string())
+        .add_attribute("store_name", offer.store_name.clone());
+    if let Some(expires_at) = offer.expires_at {
+        event = event.add_attribute("expires_at", expires_at.to_string());
+    }
+    if !offer.line_quotes.is_empty() {
+        let lines: Vec<u32> = offer.line_quotes.iter().map(|quote| quote.line).collect();
+        event = event.add_attribute("quoted_lines", join(&lines));
+    }
+    event
+}
+
+pub fn offer_updated(offer: &Offer) -> Event {
+    let mut event = event("offer_updated")
+        .add_attribute("offer_id", offer.id.to_string())
+        .add_attribute("request_id", offer.request_id.to_string())
+        .add_attribute("price", offer.price.to_string())
+        .add_attribute("seller", offer.authority.to_string())
+        .add_attribute("store_name", offer.store_name.clone());
+    if let Some(expires_at) = offer.expires_at {
+        event = event.add_attribute("expires_at", expires_at.to_string());
+    }
+    if !offer.line_quotes.is_empty() {
+        let lines: Vec<u32> = offer.line_quotes.iter().map(|quote| quote.line).collect();
+        event = event.add_attribute("quoted_lines", join(&lines));
+    }
+    event
+}
+
+pub fn offer_committed(offer: &Offer) -> Event {
+    event("offer_committed")
+        .add_attribute("offer_id", offer.id.to_string())
+        .add_attribute("request_id", offer.request_id.to_string())
+        .add_attribute("seller", offer.authority.to_string())
+        .add_attribute("store_name", offer.store_name.clone())
+}
+
+pub fn offer_revealed(offer: &Offer) -> Event {
+    event("offer_revealed")
+        .add_attribute("offer_id", offer.id.to_string())
+        .add_attribute("request_id", offer.request_id.to_string())
+        .add_attribute("price", offer.price.to_string())
+        .add_attribute("seller", offer.authority.to_string())
+}
+
+pub fn offer_countered(offer: &Offer, revision: &OfferRevision, by: &Addr) -> Event {
+    event("offer_countered")
+        .add_attribute("offer_id", offer.id.to_string())
+        .add_attribute("request_id", offer.request_id.to_string())
+        .add_attribute("revision", (offer.revisions.len() - 1).to_string())
+        .add_attribute("price", revision.price.to_string())
+        .add_attribute("proposed_by", format!("{:?}", revision.proposed_by))
+        .add_attribute("sender", by.to_string())
+}
+
+pub fn counter_offer_answered(offer: &Offer, revision: &OfferRevision, by: &Addr) -> Event {
+    event("counter_offer_answered")
+        .add_attribute("offer_id", offer.id.to_string())
+        .add_attribute("request_id", offer.request_id.to_string())
+        .add_attribute("revision", (offer.revisions.len() - 1).to_string())
+        .add_attribute("price", revision.price.to_string())
+        .add_attribute("status", format!("{:?}", revision.status))
+        .add_attribute("sender", by.to_string())
+}
+
+pub fn offer_accepted(offer: &Offer, buyer: &Addr) -> Event {
+    let mut event = event("offer_accepted")
+        .add_attribute("offer_id", offer.id.to_string())
+        .add_attribute("request_id", offer.request_id.to_string())
+        .add_attribute("price", offer.accepted_price().to_string())
+        .add_attribute("seller", offer.authority.to_string())
+        .add_attribute("buyer", buyer.to_string());
+    if !offer.accepted_lines.is_empty() {
+        event = event.add_attribute("accepted_lines", join(&offer.accepted_lines));
+    }
+    event
+}
+
+pub fn offers_cancelled(request_id: u64, seller: &Addr, offer_ids: &[u64]) -> Event {
+    event("offers_cancelled")
+        .add_attribute("request_id", request_id.to_string())
+        .add_attribute("seller", seller.to_string())
+        .add_attribute("offer_ids", join(offer_ids))
+}
+
+pub fn request_paid(payment: &PaymentInfo) -> Event {
+    event("request_paid")
+        .add_attribute("request_id", payment.request_id.to_string())
+        .add_attribute("amount", payment.amount.to_string())
+        .add_attribute("coin", format!("{:?}", payment.coin))
+        .add_attribute("seller", payment.seller.to_string())
+        .add_attribute("buyer", payment.buyer.to_string())
+        .add_attribute("price_paid", payment.price_paid.to_string())
+}
+
+pub fn overpayment_refunded(request_id: u64, buyer: &Addr, amount: Uint128) -> Event {
+    event("overpayment_refunded")
+        .add_attribute("request_id", request_id.to_string())
+        .add_attribute("buyer", buyer.to_string())
+        .add_attribute("amount", amount.to_string())
+}
+
+pub fn installment_paid(payment: &PaymentInfo, amount: Uint128) -> Event {
+    let mut event = event("installment_paid")
+        .add_attribute("request_id", payment.request_id.to_string())
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("total_paid", payment.amount.to_string())
+        .add_attribute("coin", format!("{:?}", payment.coin))
+        .add_attribute("buyer", payment.buyer.to_string())
+        .add_attribute("price_paid", payment.price_paid.to_string());
+    if let Some(next_due_at) = payment.next_due_at {
+        event = event.add_attribute("next_due_at", next_due_at.to_string());
+    }
+    event
+}
+
+pub fn deposit_forfeited(payment: &PaymentInfo, forfeited: Uint128, refunded: Uint128) -> Event {
+    event("deposit_forfeited")
+        .add_attribute("request_id", payment.request_id.to_string())
+        .add_attribute("seller", payment.seller.to_string())
+        .add_attribute("buyer", payment.buyer.to_string())
+        .add_attribute("forfeited", forfeited.to_string())
+        .add_attribute("refunded", refunded.to_string())
+        .add_attribute("coin", format!("{:?}", payment.coin))
+}
+
+pub fn stake_bonded(seller: &Addr, amount: Uint128, stake: &SellerStake) -> Event {
+    event("stake_bonded")
+        .add_attribute("seller", seller.to_string())
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("bonded", stake.bonded.to_string())
+}
+
+pub fn stake_unbonding(seller: &Addr, amount: Uint128, stake: &SellerStake) -> Event {
+    event("stake_unbonding")
+        .add_attribute("seller", seller.to_string())
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("unbonding", stake.unbonding.to_string())
+        .add_attribute(
+            "unbonding_ends_at",
+            stake.unbonding_ends_at.unwrap_or_default().to_string(),
+        )
+}
+
+pub fn stake_withdrawn(seller: &Addr, amount: Uint128) -> Event {
+    event("stake_withdrawn")
+        .add_attribute("seller", seller.to_string())
+        .add_attribute("amount", amount.to_string())
+}
+
+pub fn stake_slashed(payment: &PaymentInfo, slashed: Uint128, refunded: Uint128) -> Event {
+    event("stake_slashed")
+        .add_attribute("request_id", payment.request_id.to_string())
+        .add_attribute("seller", payment.seller.to_string())
+        .add_attribute("buyer", payment.buyer.to_string())
+        .add_attribute("slashed", slashed.to_string())
+        .add_attribute("refunded", refunded.to_string())
+        .add_attribute("coin", format!("{:?}", payment.coin))
+}
+
+pub fn delivery_updated(request: &Request, seller: &Addr, milestone: &DeliveryMilestone) -> Event {
+    let mut event = event("delivery_updated")
+        .add_attribute("request_id", request.id.to_string())
+        .add_attribute("seller", seller.to_string())
+        .add_attribute("stage", format!("{:?}", milestone.stage));
+    if let Some(tracking_ref) = &milestone.tracking_ref {
+        event = event.add_attribute("tracking_ref", tracking_ref.clone());
+    }
+    event
+}
+
+pub fn request_completed(
+    request: &Request,
+    seller: &Addr,
+    amount: Uint128,
+    coin: &CoinPayment,
+) -> Event {
+    event("request_completed")
+        .add_attribute("request_id", request.id.to_string())
+        .add_attribute("seller", seller.to_string())
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("coin", format!("{:?}", coin))
+}
@@ -1,46 +1,557 @@
-// use schemars::JsonSchema;
-// use serde::{Deserialize, Serialize};
-
-// use cosmwasm_std::{
-//     to_binary, Addr, CosmosMsg, CustomQuery, Querier, QuerierWrapper, StdResult, WasmMsg, WasmQuery,
-// };
-
-// use crate::msg::{CountResponse, ExecuteMsg, QueryMsg};
-
-// /// CwTemplateContract is a wrapper around Addr that provides a lot of helpers
-// /// for working with this.
-// #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-// pub struct CwTemplateContract(pub Addr);
-
-// impl CwTemplateContract {
-//     pub fn addr(&self) -> Addr {
-//         self.0.clone()
-//     }
-
-//     pub fn call<T: Into<ExecuteMsg>>(&self, msg: T) -> StdResult<CosmosMsg> {
-//         let msg = to_binary(&msg.into())?;
-//         Ok(WasmMsg::Execute {
-//             contract_addr: self.addr().into(),
-//             msg,
-//             funds: vec![],
-//         }
-//         .into())
-//     }
-
-//     /// Get Count
-//     pub fn count<Q, T, CQ>(&self, querier: &Q) -> StdResult<CountResponse>
-//     where
-//         Q: Querier,
-//         T: Into<String>,
-//         CQ: CustomQuery,
-//     {
-//         let msg = QueryMsg::GetCount {};
-//         let query = WasmQuery::Smart {
-//             contract_addr: self.addr().into(),
-//             msg: to_binary(&msg)?,
-//         }
-//         .into();
-//         let res: CountResponse = QuerierWrapper::<CQ>::new(querier).query(&query)?;
-//         Ok(res)
-//     }
-// }
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_binary, Addr, Coin, CosmosMsg, CustomQuery, HexBinary, QuerierWrapper, StdResult,
+    Uint128, WasmMsg,
+};
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+
+use crate::msg::{
+    ConfigResponse, DeliveryStatusResponse, ExecuteMsg, LocationPreferenceResponse,
+    ModeratorsResponse, OfferResponse, OffersResponse, PauseStatusResponse, PaymentHistoryResponse,
+    PaymentQuoteResponse, QueryMsg, RequestResponse, RequestsResponse, SellerStakeResponse,
+    StoresResponse, UserResponse,
+};
+use crate::state::{
+    AccountType, AutoAccept, Budget, CoinPayment, DeliveryStage, LineItem, LineQuote,
+    PaymentSchedule, SealedAuction,
+};
+
+/// Commitment for a sealed bid: the sha256 of `"{request_id}:{seller}:{price}:{salt}"`.
+/// Binding the request and seller stops anyone from replaying a revealed bid.
+pub fn bid_commitment(request_id: u64, seller: &str, price: u128, salt: &str) -> HexBinary {
+    let preimage = format!("{}:{}:{}:{}", request_id, seller, price, salt);
+    HexBinary::from(Sha256::digest(preimage.as_bytes()).as_slice())
+}
+
+/// Delivery code for a request: the sha256 of `"{request_id}:{secret}"`. The
+/// hash is public once paid, so the secret must not be guessable.
+pub fn delivery_code(request_id: u64, secret: &str) -> HexBinary {
+    let preimage = format!("{}:{}", request_id, secret);
+    HexBinary::from(Sha256::digest(preimage.as_bytes()).as_slice())
+}
+
+/// MarketplaceContract is a wrapper around Addr that provides typed helpers
+/// for calling and querying a deployed marketplace from another contract.
+#[cw_serde]
+pub struct MarketplaceContract(pub Addr);
+
+impl MarketplaceContract {
+    pub fn addr(&self) -> Addr {
+        self.0.clone()
+    }
+
+    pub fn call<T: Into<ExecuteMsg>>(&self, msg: T) -> StdResult<CosmosMsg> {
+        self.call_with_funds(msg, vec![])
+    }
+
+    pub fn call_with_funds<T: Into<ExecuteMsg>>(
+        &self,
+        msg: T,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = to_json_binary(&msg.into())?;
+        Ok(WasmMsg::Execute {
+            contract_addr: self.addr().into(),
+            msg,
+            funds,
+        }
+        .into())
+    }
+
+    pub fn create_user(
+        &self,
+        username: impl Into<String>,
+        phone: impl Into<String>,
+        latitude: i128,
+        longitude: i128,
+        account_type: AccountType,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::CreateUser {
+            username: username.into(),
+            phone: phone.into(),
+            latitude,
+            longitude,
+            account_type,
+        })
+    }
+
+    pub fn update_user(
+        &self,
+        username: impl Into<String>,
+        phone: impl Into<String>,
+        latitude: i128,
+        longitude: i128,
+        account_type: AccountType,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::UpdateUser {
+            username: username.into(),
+            phone: phone.into(),
+            latitude,
+            longitude,
+            account_type,
+        })
+    }
+
+    pub fn create_store(
+        &self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        phone: impl Into<String>,
+        latitude: i128,
+        longitude: i128,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::CreateStore {
+            name: name.into(),
+            description: description.into(),
+            phone: phone.into(),
+            latitude,
+            longitude,
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_request(
+        &self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        images: Vec<String>,
+        latitude: i128,
+        longitude: i128,
+        expires_at: Option<u64>,
+        time_to_lock: Option<u64>,
+        line_items: Vec<LineItem>,
+        budget: Option<Budget>,
+        auction: Option<SealedAuction>,
+        auto_accept: Option<AutoAccept>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::CreateRequest {
+            name: name.into(),
+            description: description.into(),
+            images,
+            latitude,
+            longitude,
+            expires_at,
+            time_to_lock,
+            line_items,
+            budget,
+            auction,
+            auto_accept,
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_offer(
+        &self,
+        request_id: u64,
+        price: u128,
+        images: Vec<String>,
+        store_name: impl Into<String>,
+        expires_at: Option<u64>,
+        payment_schedule: Option<PaymentSchedule>,
+        line_quotes: Vec<LineQuote>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::CreateOffer {
+            price,
+            images,
+            request_id,
+            store_name: store_name.into(),
+            expires_at,
+            payment_schedule,
+            line_quotes,
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_offer(
+        &self,
+        offer_id: u64,
+        price: u128,
+        images: Vec<String>,
+        store_name: impl Into<String>,
+        expires_at: Option<u64>,
+        payment_schedule: Option<PaymentSchedule>,
+        line_quotes: Vec<LineQuote>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::UpdateOffer {
+            offer_id,
+            price,
+            images,
+            store_name: store_name.into(),
+            expires_at,
+            payment_schedule,
+            line_quotes,
+        })
+    }
+
+    pub fn commit_offer(
+        &self,
+        request_id: u64,
+        commitment: HexBinary,
+        images: Vec<String>,
+        store_name: impl Into<String>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::CommitOffer {
+            request_id,
+            commitment,
+            images,
+            store_name: store_name.into(),
+        })
+    }
+
+    pub fn reveal_offer(
+        &self,
+        offer_id: u64,
+        price: u128,
+        salt: impl Into<String>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::RevealOffer {
+            offer_id,
+            price,
+            salt: salt.into(),
+        })
+    }
+
+    pub fn settle_auction(&self, request_id: u64) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::SettleAuction { request_id })
+    }
+
+    pub fn finalize_request(&self, request_id: u64) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::FinalizeRequest { request_id })
+    }
+
+    pub fn counter_offer(&self, offer_id: u64, price: u128) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::CounterOffer { offer_id, price })
+    }
+
+    pub fn respond_to_counter_offer(&self, offer_id: u64, accept: bool) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::RespondToCounterOffer { offer_id, accept })
+    }
+
+    pub fn accept_offer(&self, offer_id: u64, lines: Option<Vec<u32>>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::AcceptOffer { offer_id, lines })
+    }
+
+    pub fn toggle_location(&self, enabled: bool) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::ToggleLocation { enabled })
+    }
+
+    pub fn delete_request(&self, request_id: u64) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::DeleteRequest { request_id })
+    }
+
+    pub fn cancel_request(&self, request_id: u64) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::CancelRequest { request_id })
+    }
+
+    pub fn mark_request_as_completed(&self, request_id: u64) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::MarkRequestAsCompleted { request_id })
+    }
+
+    /// Native payments must attach the amount due in `funds`; CW20 payments
+    /// attach nothing but require an allowance for the marketplace on the token.
+    /// `payment_quote` tells how much is due.
+    pub fn pay_for_request(
+        &self,
+        request_id: u64,
+        coin: CoinPayment,
+        max_amount: Option<Uint128>,
+        delivery_code: Option<HexBinary>,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::PayForRequest {
+            request_id,
+            coin,
+            max_amount,
+            delivery_code,
+        };
+        self.call_with_funds(msg, funds)
+    }
+
+    pub fn post_delivery_milestone(
+        &self,
+        request_id: u64,
+        stage: DeliveryStage,
+        tracking_ref: Option<String>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::PostDeliveryMilestone {
+            request_id,
+            stage,
+            tracking_ref,
+        })
+    }
+
+    pub fn confirm_delivery(
+        &self,
+        request_id: u64,
+        secret: impl Into<String>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::ConfirmDelivery {
+            request_id,
+            secret: secret.into(),
+        })
+    }
+
+    pub fn release_escrow(&self, request_id: u64) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::ReleaseEscrow { request_id })
+    }
+
+    pub fn forfeit_deposit(&self, request_id: u64) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::ForfeitDeposit { request_id })
+    }
+
+    /// Bonds `funds`, which must be in `state::COIN_DENOM`.
+    pub fn bond_stake(&self, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        self.call_with_funds(ExecuteMsg::BondStake {}, funds)
+    }
+
+    pub fn unbond_stake(&self, amount: Uint128) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::UnbondStake { amount })
+    }
+
+    pub fn withdraw_stake(&self) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::WithdrawStake {})
+    }
+
+    pub fn slash_stake(&self, request_id: u64) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::SlashStake { request_id })
+    }
+
+    pub fn set_pause(
+        &self,
+        all: Option<bool>,
+        payments: Option<bool>,
+        listings: Option<bool>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::SetPause {
+            all,
+            payments,
+            listings,
+        })
+    }
+
+    pub fn add_moderator(&self, address: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::AddModerator {
+            address: address.into(),
+        })
+    }
+
+    pub fn remove_moderator(&self, address: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::RemoveModerator {
+            address: address.into(),
+        })
+    }
+
+    pub fn suspend_user(&self, address: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::SuspendUser {
+            address: address.into(),
+        })
+    }
+
+    pub fn unsuspend_user(&self, address: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::UnsuspendUser {
+            address: address.into(),
+        })
+    }
+
+    pub fn set_request_hidden(&self, request_id: u64, hidden: bool) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::SetRequestHidden { request_id, hidden })
+    }
+
+    pub fn set_offer_hidden(&self, offer_id: u64, hidden: bool) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::SetOfferHidden { offer_id, hidden })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_config(
+        &self,
+        time_to_lock: Option<u64>,
+        min_time_to_lock: Option<u64>,
+        max_time_to_lock: Option<u64>,
+        oracle: Option<String>,
+        min_stake: Option<Uint128>,
+        unbonding_period: Option<u64>,
+        max_open_requests: Option<u32>,
+        max_offers_per_request: Option<u32>,
+        max_sellers_per_request: Option<u32>,
+        creation_fee: Option<Uint128>,
+        auto_release_after: Option<u64>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::UpdateConfig {
+            time_to_lock,
+            min_time_to_lock,
+            max_time_to_lock,
+            oracle,
+            min_stake,
+            unbonding_period,
+            max_open_requests,
+            max_offers_per_request,
+            max_sellers_per_request,
+            creation_fee,
+            auto_release_after,
+        })
+    }
+
+    fn query<CQ: CustomQuery, T: DeserializeOwned>(
+        &self,
+        querier: &QuerierWrapper<CQ>,
+        msg: &QueryMsg,
+    ) -> StdResult<T> {
+        querier.query_wasm_smart(self.addr(), msg)
+    }
+
+    pub fn user<CQ: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<CQ>,
+        address: impl Into<String>,
+    ) -> StdResult<UserResponse> {
+        let address = address.into();
+        self.query(querier, &QueryMsg::GetUser { address })
+    }
+
+    pub fn user_by_id<CQ: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<CQ>,
+        user_id: u64,
+    ) -> StdResult<UserResponse> {
+        self.query(querier, &QueryMsg::GetUserById { user_id })
+    }
+
+    pub fn location_preference<CQ: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<CQ>,
+        address: impl Into<String>,
+    ) -> StdResult<LocationPreferenceResponse> {
+        let address = address.into();
+        self.query(querier, &QueryMsg::GetLocationPreference { address })
+    }
+
+    pub fn user_stores<CQ: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<CQ>,
+        address: impl Into<String>,
+    ) -> StdResult<StoresResponse> {
+        let address = address.into();
+        self.query(querier, &QueryMsg::GetUserStores { address })
+    }
+
+    pub fn request<CQ: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<CQ>,
+        request_id: u64,
+    ) -> StdResult<RequestResponse> {
+        self.query(querier, &QueryMsg::GetRequest { request_id })
+    }
+
+    pub fn all_requests<CQ: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<CQ>,
+    ) -> StdResult<RequestsResponse> {
+        self.query(querier, &QueryMsg::GetAllRequests {})
+    }
+
+    pub fn requests_by_budget<CQ: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<CQ>,
+        min_price: Option<u128>,
+        max_price: Option<u128>,
+        coin: Option<CoinPayment>,
+    ) -> StdResult<RequestsResponse> {
+        let msg = QueryMsg::GetRequestsByBudget {
+            min_price,
+            max_price,
+            coin,
+        };
+        self.query(querier, &msg)
+    }
+
+    pub fn user_requests<CQ: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<CQ>,
+        address: impl Into<String>,
+    ) -> StdResult<RequestsResponse> {
+        let address = address.into();
+        self.query(querier, &QueryMsg::GetUserRequests { address })
+    }
+
+    pub fn user_payment_history<CQ: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<CQ>,
+        address: impl Into<String>,
+    ) -> StdResult<PaymentHistoryResponse> {
+        let address = address.into();
+        self.query(querier, &QueryMsg::GetUserPaymentHistory { address })
+    }
+
+    pub fn offer<CQ: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<CQ>,
+        offer_id: u64,
+    ) -> StdResult<OfferResponse> {
+        self.query(querier, &QueryMsg::GetOffer { offer_id })
+    }
+
+    pub fn offers_by_request<CQ: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<CQ>,
+        request_id: u64,
+    ) -> StdResult<OffersResponse> {
+        self.query(querier, &QueryMsg::GetOffersByRequest { request_id })
+    }
+
+    pub fn seller_offers<CQ: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<CQ>,
+        address: impl Into<String>,
+    ) -> StdResult<OffersResponse> {
+        let address = address.into();
+        self.query(querier, &QueryMsg::GetSellerOffers { address })
+    }
+
+    pub fn config<CQ: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<CQ>,
+    ) -> StdResult<ConfigResponse> {
+        self.query(querier, &QueryMsg::GetConfig {})
+    }
+
+    pub fn seller_stake<CQ: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<CQ>,
+        address: impl Into<String>,
+    ) -> StdResult<SellerStakeResponse> {
+        let address = address.into();
+        self.query(querier, &QueryMsg::GetSellerStake { address })
+    }
+
+    pub fn pause_status<CQ: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<CQ>,
+    ) -> StdResult<PauseStatusResponse> {
+        self.query(querier, &QueryMsg::PauseStatus {})
+    }
+
+    pub fn moderators<CQ: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<CQ>,
+    ) -> StdResult<ModeratorsResponse> {
+        self.query(querier, &QueryMsg::GetModerators {})
+    }
+
+    pub fn delivery_status<CQ: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<CQ>,
+        request_id: u64,
+    ) -> StdResult<DeliveryStatusResponse> {
+        self.query(querier, &QueryMsg::GetDeliveryStatus { request_id })
+    }
+
+    pub fn payment_quote<CQ: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<CQ>,
+        request_id: u64,
+        coin: CoinPayment,
+    ) -> StdResult<PaymentQuoteResponse> {
+        self.query(querier, &QueryMsg::GetPaymentQuote { request_id, coin })
+    }
+}
@@ -1,71 +1,3935 @@
-// #[cfg(test)]
-// mod tests {
-//     use crate::helpers::CwTemplateContract;
-//     use crate::msg::InstantiateMsg;
-//     use cosmwasm_std::{Addr, Coin, Empty, Uint128};
-//     use cw_multi_test::{App, AppBuilder, Contract, ContractWrapper, Executor};
-
-//     pub fn contract_template() -> Box<dyn Contract<Empty>> {
-//         let contract = ContractWrapper::new(
-//             crate::contract::execute,
-//             crate::contract::instantiate,
-//             crate::contract::query,
-//         );
-//         Box::new(contract)
-//     }
-
-//     const USER: &str = "USER";
-//     const ADMIN: &str = "ADMIN";
-//     const NATIVE_DENOM: &str = "denom";
-
-//     fn mock_app() -> App {
-//         AppBuilder::new().build(|router, _, storage| {
-//             router
-//                 .bank
-//                 .init_balance(
-//                     storage,
-//                     &Addr::unchecked(USER),
-//                     vec![Coin {
-//                         denom: NATIVE_DENOM.to_string(),
-//                         amount: Uint128::new(1),
-//                     }],
-//                 )
-//                 .unwrap();
-//         })
-//     }
-
-//     fn proper_instantiate() -> (App, CwTemplateContract) {
-//         let mut app = mock_app();
-//         let cw_template_id = app.store_code(contract_template());
-
-//         let msg = InstantiateMsg { count: 1i32 };
-//         let cw_template_contract_addr = app
-//             .instantiate_contract(
-//                 cw_template_id,
-//                 Addr::unchecked(ADMIN),
-//                 &msg,
-//                 &[],
-//                 "test",
-//                 None,
-//             )
-//             .unwrap();
-
-//         let cw_template_contract = CwTemplateContract(cw_template_contract_addr);
-
-//         (app, cw_template_contract)
-//     }
-
-//     mod count {
-//         use super::*;
-//         use crate::msg::ExecuteMsg;
-
-//         #[test]
-//         fn count() {
-//             let (mut app, cw_template_contract) = proper_instantiate();
-
-//             let msg = ExecuteMsg::Increment {};
-//             let cosmos_msg = cw_template_contract.call(msg).unwrap();
-//             app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
-//         }
-//     }
-// }
+use cosmwasm_std::{coin, coins, Addr, Coin, CosmosMsg, Decimal, Empty, Uint128};
+use cw_multi_test::{App, AppResponse, Contract, ContractWrapper, Executor};
+
+use crate::helpers::MarketplaceContract;
+use crate::msg::InstantiateMsg;
+use crate::state::{
+    AccountType, CoinPayment, RequestLifecycle, SellerStake, COIN_DENOM, TIME_TO_LOCK,
+};
+use crate::MarketplaceError;
+
+const INITIAL_BALANCE: u128 = 1_000_000;
+// Held by every account but never accepted by the marketplace.
+const OTHER_DENOM: &str = "uatom";
+const PHONE: &str = "+2348012345678";
+
+pub fn contract_marketplace() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        crate::contract::execute,
+        crate::contract::instantiate,
+        crate::contract::query,
+    );
+    Box::new(contract)
+}
+
+/// Bare-bones CW20 standing in for the USDT contract: balances, allowances,
+/// `Transfer`, `TransferFrom` and the `Balance` query are all the marketplace needs.
+mod mock_cw20 {
+    use cosmwasm_schema::cw_serde;
+    use cosmwasm_std::{
+        to_json_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError,
+        StdResult, Uint128,
+    };
+    use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg};
+    use cw_storage_plus::Map;
+
+    const BALANCES: Map<&Addr, Uint128> = Map::new("balances");
+    const ALLOWANCES: Map<(&Addr, &Addr), Uint128> = Map::new("allowances");
+
+    #[cw_serde]
+    pub struct InstantiateMsg {
+        pub balances: Vec<(String, Uint128)>,
+    }
+
+    pub fn instantiate(
+        deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        msg: InstantiateMsg,
+    ) -> StdResult<Response> {
+        for (address, amount) in msg.balances {
+            let address = deps.api.addr_validate(&address)?;
+            BALANCES.save(deps.storage, &address, &amount)?;
+        }
+        Ok(Response::new())
+    }
+
+    fn move_tokens(deps: DepsMut, from: &Addr, to: &Addr, amount: Uint128) -> StdResult<()> {
+        let from_balance = BALANCES.may_load(deps.storage, from)?.unwrap_or_default();
+        BALANCES.save(deps.storage, from, &from_balance.checked_sub(amount)?)?;
+        BALANCES.update(deps.storage, to, |balance| -> StdResult<_> {
+            Ok(balance.unwrap_or_default() + amount)
+        })?;
+        Ok(())
+    }
+
+    pub fn execute(
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        msg: Cw20ExecuteMsg,
+    ) -> StdResult<Response> {
+        match msg {
+            Cw20ExecuteMsg::Transfer { recipient, amount } => {
+                let recipient = deps.api.addr_validate(&recipient)?;
+                move_tokens(deps, &info.sender, &recipient, amount)?;
+            }
+            Cw20ExecuteMsg::TransferFrom {
+                owner,
+                recipient,
+                amount,
+            } => {
+                let owner = deps.api.addr_validate(&owner)?;
+                let recipient = deps.api.addr_validate(&recipient)?;
+                let key = (&owner, &info.sender);
+                let allowance = ALLOWANCES.may_load(deps.storage, key)?.unwrap_or_default();
+                ALLOWANCES.save(deps.storage, key, &allowance.checked_sub(amount)?)?;
+                move_tokens(deps, &owner, &recipient, amount)?;
+            }
+            Cw20ExecuteMsg::IncreaseAllowance {
+                spender, amount, ..
+            } => {
+                let spender = deps.api.addr_validate(&spender)?;
+                ALLOWANCES.update(
+                    deps.storage,
+                    (&info.sender, &spender),
+                    |allowance| -> StdResult<_> { Ok(allowance.unwrap_or_default() + amount) },
+                )?;
+            }
+            _ => return Err(StdError::generic_err("unsupported by mock cw20")),
+        }
+        Ok(Response::new())
+    }
+
+    pub fn query(deps: Deps, _env: Env, msg: Cw20QueryMsg) -> StdResult<Binary> {
+        match msg {
+            Cw20QueryMsg::Balance { address } => {
+                let address = deps.api.addr_validate(&address)?;
+                let balance = BALANCES
+                    .may_load(deps.storage, &address)?
+                    .unwrap_or_default();
+                to_json_binary(&BalanceResponse { balance })
+            }
+            _ => Err(StdError::generic_err("unsupported by mock cw20")),
+        }
+    }
+}
+
+pub fn contract_mock_cw20() -> Box<dyn Contract<Empty>> {
+    let contract =
+        ContractWrapper::new(mock_cw20::execute, mock_cw20::instantiate, mock_cw20::query);
+    Box::new(contract)
+}
+
+/// Price oracle with prices set by whoever calls it.
+mod mock_oracle {
+    use cosmwasm_schema::cw_serde;
+    use cosmwasm_std::{
+        to_json_binary, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Response, StdError,
+        StdResult,
+    };
+    use cw_storage_plus::Map;
+
+    use crate::oracle::{OracleQueryMsg, PriceResponse};
+
+    const PRICES: Map<&str, Decimal> = Map::new("prices");
+
+    #[cw_serde]
+    pub struct InstantiateMsg {}
+
+    #[cw_serde]
+    pub enum ExecuteMsg {
+        SetPrice { denom: String, price: Decimal },
+    }
+
+    pub fn instantiate(
+        _deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        _msg: InstantiateMsg,
+    ) -> StdResult<Response> {
+        Ok(Response::new())
+    }
+
+    pub fn execute(
+        deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        msg: ExecuteMsg,
+    ) -> StdResult<Response> {
+        match msg {
+            ExecuteMsg::SetPrice { denom, price } => {
+                PRICES.save(deps.storage, &denom, &price)?;
+            }
+        }
+        Ok(Response::new())
+    }
+
+    pub fn query(deps: Deps, _env: Env, msg: OracleQueryMsg) -> StdResult<Binary> {
+        match msg {
+            OracleQueryMsg::Price { denom } => {
+                let price = PRICES
+                    .may_load(deps.storage, &denom)?
+                    .ok_or_else(|| StdError::generic_err(format!("no price for {}", denom)))?;
+                to_json_binary(&PriceResponse { price })
+            }
+        }
+    }
+}
+
+pub fn contract_mock_oracle() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        mock_oracle::execute,
+        mock_oracle::instantiate,
+        mock_oracle::query,
+    );
+    Box::new(contract)
+}
+
+struct Suite {
+    app: App,
+    marketplace: MarketplaceContract,
+    usdt: Addr,
+    admin: Addr,
+    buyer: Addr,
+    seller: Addr,
+}
+
+impl Suite {
+    fn new() -> Self {
+        let mut app = App::default();
+        let admin = app.api().addr_make("admin");
+        let buyer = app.api().addr_make("buyer");
+        let seller = app.api().addr_make("seller");
+
+        app.init_modules(|router, _, storage| {
+            for account in [&buyer, &seller] {
+                router
+                    .bank
+                    .init_balance(
+                        storage,
+                        account,
+                        vec![
+                            coin(INITIAL_BALANCE, COIN_DENOM),
+                            coin(INITIAL_BALANCE, OTHER_DENOM),
+                        ],
+                    )
+                    .unwrap();
+            }
+        });
+
+        let usdt_id = app.store_code(contract_mock_cw20());
+        let usdt = app
+            .instantiate_contract(
+                usdt_id,
+                admin.clone(),
+                &mock_cw20::InstantiateMsg {
+                    balances: vec![(buyer.to_string(), Uint128::new(INITIAL_BALANCE))],
+                },
+                &[],
+                "usdt",
+                None,
+            )
+            .unwrap();
+
+        let marketplace_id = app.store_code(contract_marketplace());
+        let marketplace = app
+            .instantiate_contract(
+                marketplace_id,
+                admin.clone(),
+                &InstantiateMsg {
+                    time_to_lock: None,
+                    min_time_to_lock: None,
+                    max_time_to_lock: None,
+                    usdt_addr: Some(usdt.to_string()),
+                    oracle: None,
+                    min_stake: None,
+                    unbonding_period: None,
+                    max_open_requests: None,
+                    max_offers_per_request: None,
+                    max_sellers_per_request: None,
+                    creation_fee: None,
+                    auto_release_after: None,
+                },
+                &[],
+                "marketplace",
+                None,
+            )
+            .unwrap();
+
+        let mut suite = Suite {
+            app,
+            marketplace: MarketplaceContract(marketplace),
+            usdt,
+            admin,
+            buyer,
+            seller,
+        };
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        suite.register(&buyer, AccountType::Buyer).unwrap();
+        suite.register(&seller, AccountType::Seller).unwrap();
+        suite
+    }
+
+    fn execute(&mut self, sender: &Addr, msg: CosmosMsg) -> Result<AppResponse, MarketplaceError> {
+        self.app
+            .execute(sender.clone(), msg)
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    fn advance(&mut self, seconds: u64) {
+        self.app
+            .update_block(|block| block.time = block.time.plus_seconds(seconds));
+    }
+
+    fn now(&self) -> u64 {
+        self.app.block_info().time.seconds()
+    }
+
+    fn user(&mut self, name: &str, account_type: AccountType) -> Addr {
+        let addr = self.app.api().addr_make(name);
+        self.register(&addr, account_type).unwrap();
+        addr
+    }
+
+    fn register(
+        &mut self,
+        sender: &Addr,
+        account_type: AccountType,
+    ) -> Result<AppResponse, MarketplaceError> {
+        let msg = self
+            .marketplace
+            .create_user("user", PHONE, 6_524_379, 3_379_206, account_type)
+            .unwrap();
+        self.execute(sender, msg)
+    }
+
+    fn create_store(&mut self, sender: &Addr) -> Result<AppResponse, MarketplaceError> {
+        let msg = self
+            .marketplace
+            .create_store("Store", "Groceries", PHONE, 6_524_379, 3_379_206)
+            .unwrap();
+        self.execute(sender, msg)
+    }
+
+    fn try_create_request(
+        &mut self,
+        sender: &Addr,
+        expires_at: Option<u64>,
+    ) -> Result<u64, MarketplaceError> {
+        let msg = self
+            .marketplace
+            .create_request(
+                "Rice",
+                "50kg bag",
+                vec![],
+                6_524_379,
+                3_379_206,
+                expires_at,
+                None,
+                vec![],
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        let res = self.execute(sender, msg)?;
+        Ok(event_id(&res, "marketplace/request_created", "request_id"))
+    }
+
+    fn create_request(&mut self) -> u64 {
+        let buyer = self.buyer.clone();
+        self.try_create_request(&buyer, None).unwrap()
+    }
+
+    fn try_create_offer(
+        &mut self,
+        sender: &Addr,
+        request_id: u64,
+        price: u128,
+        expires_at: Option<u64>,
+    ) -> Result<u64, MarketplaceError> {
+        let msg = self
+            .marketplace
+            .create_offer(request_id, price, vec![], "Store", expires_at, None, vec![])
+            .unwrap();
+        let res = self.execute(sender, msg)?;
+        Ok(event_id(&res, "marketplace/offer_created", "offer_id"))
+    }
+
+    fn create_offer(&mut self, request_id: u64, price: u128) -> u64 {
+        let seller = self.seller.clone();
+        self.try_create_offer(&seller, request_id, price, None)
+            .unwrap()
+    }
+
+    fn accept(&mut self, sender: &Addr, offer_id: u64) -> Result<AppResponse, MarketplaceError> {
+        let msg = self.marketplace.accept_offer(offer_id, None).unwrap();
+        self.execute(sender, msg)
+    }
+
+    fn pay(
+        &mut self,
+        sender: &Addr,
+        request_id: u64,
+        coin: CoinPayment,
+        funds: Vec<Coin>,
+    ) -> Result<AppResponse, MarketplaceError> {
+        let msg = self
+            .marketplace
+            .pay_for_request(request_id, coin, None, None, funds)
+            .unwrap();
+        self.execute(sender, msg)
+    }
+
+    /// Deploys a mock oracle and points the marketplace at it.
+    fn use_oracle(&mut self) -> Addr {
+        let oracle_id = self.app.store_code(contract_mock_oracle());
+        let oracle = self
+            .app
+            .instantiate_contract(
+                oracle_id,
+                self.admin.clone(),
+                &mock_oracle::InstantiateMsg {},
+                &[],
+                "oracle",
+                None,
+            )
+            .unwrap();
+        let msg = self
+            .marketplace
+            .update_config(
+                None,
+                None,
+                None,
+                Some(oracle.to_string()),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        let admin = self.admin.clone();
+        self.execute(&admin, msg).unwrap();
+        oracle
+    }
+
+    fn set_price(&mut self, oracle: &Addr, denom: &str, price: &str) {
+        let msg = mock_oracle::ExecuteMsg::SetPrice {
+            denom: denom.to_string(),
+            price: price.parse::<Decimal>().unwrap(),
+        };
+        self.app
+            .execute_contract(self.admin.clone(), oracle.clone(), &msg, &[])
+            .unwrap();
+    }
+
+    fn approve_usdt(&mut self, owner: &Addr, amount: u128) {
+        let msg = cw20::Cw20ExecuteMsg::IncreaseAllowance {
+            spender: self.marketplace.addr().to_string(),
+            amount: Uint128::new(amount),
+            expires: None,
+        };
+        self.app
+            .execute_contract(owner.clone(), self.usdt.clone(), &msg, &[])
+            .unwrap();
+    }
+
+    fn complete(
+        &mut self,
+        sender: &Addr,
+        request_id: u64,
+    ) -> Result<AppResponse, MarketplaceError> {
+        let msg = self
+            .marketplace
+            .mark_request_as_completed(request_id)
+            .unwrap();
+        self.execute(sender, msg)
+    }
+
+    /// Runs a request from creation up to an accepted offer whose lock window
+    /// has elapsed, so it is ready to be paid.
+    fn accepted_request(&mut self, price: u128) -> u64 {
+        let request_id = self.create_request();
+        let offer_id = self.create_offer(request_id, price);
+        let buyer = self.buyer.clone();
+        self.accept(&buyer, offer_id).unwrap();
+        self.advance(TIME_TO_LOCK);
+        request_id
+    }
+
+    fn native_balance(&self, addr: &Addr) -> u128 {
+        self.app
+            .wrap()
+            .query_balance(addr, COIN_DENOM)
+            .unwrap()
+            .amount
+            .u128()
+    }
+
+    fn usdt_balance(&self, addr: &Addr) -> u128 {
+        let res: cw20::BalanceResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &self.usdt,
+                &cw20::Cw20QueryMsg::Balance {
+                    address: addr.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance.u128()
+    }
+
+    fn lifecycle(&self, request_id: u64) -> RequestLifecycle {
+        self.marketplace
+            .request(&self.app.wrap(), request_id)
+            .unwrap()
+            .request
+            .lifecycle
+    }
+}
+
+fn event_id(res: &AppResponse, ty: &str, key: &str) -> u64 {
+    let ty = format!("wasm-{}", ty);
+    res.events
+        .iter()
+        .filter(|event| event.ty == ty)
+        .flat_map(|event| event.attributes.iter())
+        .find(|attr| attr.key == key)
+        .unwrap()
+        .value
+        .parse()
+        .unwrap()
+}
+
+mod lifecycle {
+    use super::*;
+
+    #[test]
+    fn native_payment_releases_escrow_to_seller() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let marketplace = suite.marketplace.addr();
+        suite.create_store(&seller).unwrap();
+
+        let request_id = suite.create_request();
+        assert_eq!(suite.lifecycle(request_id), RequestLifecycle::Pending);
+        let offer_id = suite.create_offer(request_id, 500);
+        assert_eq!(
+            suite.lifecycle(request_id),
+            RequestLifecycle::AcceptedBySeller
+        );
+
+        suite.accept(&buyer, offer_id).unwrap();
+        assert_eq!(
+            suite.lifecycle(request_id),
+            RequestLifecycle::AcceptedByBuyer
+        );
+
+        suite.advance(TIME_TO_LOCK);
+        suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(500, COIN_DENOM),
+            )
+            .unwrap();
+        assert_eq!(suite.lifecycle(request_id), RequestLifecycle::Paid);
+        assert_eq!(suite.native_balance(&marketplace), 500);
+        assert_eq!(suite.native_balance(&buyer), INITIAL_BALANCE - 500);
+
+        suite.advance(TIME_TO_LOCK);
+        suite.complete(&buyer, request_id).unwrap();
+        assert_eq!(suite.lifecycle(request_id), RequestLifecycle::Completed);
+        assert_eq!(suite.native_balance(&marketplace), 0);
+        assert_eq!(suite.native_balance(&seller), INITIAL_BALANCE + 500);
+
+        let history = suite
+            .marketplace
+            .user_payment_history(&suite.app.wrap(), buyer.to_string())
+            .unwrap();
+        assert_eq!(history.payments.len(), 1);
+        assert_eq!(history.payments[0].amount, Uint128::new(500));
+        assert_eq!(history.payments[0].seller, seller);
+    }
+
+    #[test]
+    fn native_overpayment_is_refunded() {
+        let mut suite = Suite::new();
+        let buyer = suite.buyer.clone();
+        let marketplace = suite.marketplace.addr();
+
+        let request_id = suite.accepted_request(500);
+        let res = suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(800, COIN_DENOM),
+            )
+            .unwrap();
+        assert_eq!(
+            event_id(&res, "marketplace/overpayment_refunded", "amount"),
+            300
+        );
+        assert_eq!(suite.native_balance(&marketplace), 500);
+        assert_eq!(suite.native_balance(&buyer), INITIAL_BALANCE - 500);
+
+        let history = suite
+            .marketplace
+            .user_payment_history(&suite.app.wrap(), buyer.to_string())
+            .unwrap();
+        assert_eq!(history.payments[0].amount, Uint128::new(500));
+    }
+
+    #[test]
+    fn usdt_payment_releases_escrow_to_seller() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let marketplace = suite.marketplace.addr();
+
+        let request_id = suite.accepted_request(700);
+        suite.approve_usdt(&buyer, 700);
+        suite
+            .pay(&buyer, request_id, CoinPayment::USDT, vec![])
+            .unwrap();
+        assert_eq!(suite.usdt_balance(&marketplace), 700);
+        assert_eq!(suite.usdt_balance(&buyer), INITIAL_BALANCE - 700);
+
+        suite.advance(TIME_TO_LOCK);
+        suite.complete(&buyer, request_id).unwrap();
+        assert_eq!(suite.usdt_balance(&marketplace), 0);
+        assert_eq!(suite.usdt_balance(&seller), 700);
+    }
+
+    #[test]
+    fn buyer_switches_offer_before_lock() {
+        let mut suite = Suite::new();
+        let buyer = suite.buyer.clone();
+        let other_seller = suite.user("other_seller", AccountType::Seller);
+
+        let request_id = suite.create_request();
+        let first = suite.create_offer(request_id, 500);
+        let second = suite
+            .try_create_offer(&other_seller, request_id, 450, None)
+            .unwrap();
+
+        suite.accept(&buyer, first).unwrap();
+        suite.advance(TIME_TO_LOCK / 2);
+        suite.accept(&buyer, second).unwrap();
+
+        let res = suite
+            .marketplace
+            .request(&suite.app.wrap(), request_id)
+            .unwrap();
+        assert_eq!(res.request.accepted_offer_id, second);
+        assert_eq!(res.request.seller_price_quote, 450);
+        assert_eq!(res.locked_until, Some(suite.now() + TIME_TO_LOCK));
+
+        // The window restarts from the latest acceptance.
+        suite.advance(TIME_TO_LOCK / 2 + 1);
+        let err = suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(450, COIN_DENOM),
+            )
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestNotLocked));
+
+        suite.advance(TIME_TO_LOCK);
+        suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(450, COIN_DENOM),
+            )
+            .unwrap();
+        let payment = suite
+            .marketplace
+            .user_payment_history(&suite.app.wrap(), buyer.to_string())
+            .unwrap()
+            .payments
+            .remove(0);
+        assert_eq!(payment.seller, other_seller);
+    }
+
+    #[test]
+    fn cancelled_request_disappears_from_listings() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let kept = suite.create_request();
+        let cancelled = suite.create_request();
+        suite.create_offer(cancelled, 100);
+
+        let msg = suite.marketplace.cancel_request(cancelled).unwrap();
+        suite.execute(&buyer, msg).unwrap();
+
+        let listed = suite.marketplace.all_requests(&suite.app.wrap()).unwrap();
+        let ids: Vec<u64> = listed.requests.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![kept]);
+        let listed = suite
+            .marketplace
+            .user_requests(&suite.app.wrap(), buyer.to_string())
+            .unwrap();
+        let ids: Vec<u64> = listed.requests.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![kept]);
+
+        let offers = suite
+            .marketplace
+            .seller_offers(&suite.app.wrap(), seller.to_string())
+            .unwrap()
+            .offers;
+        assert!(offers.is_empty());
+        let offers = suite
+            .marketplace
+            .offers_by_request(&suite.app.wrap(), cancelled)
+            .unwrap()
+            .offers;
+        assert!(offers.is_empty());
+    }
+
+    #[test]
+    fn expired_items_are_hidden_and_rejected() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let now = suite.now();
+
+        let err = suite.try_create_request(&buyer, Some(now)).unwrap_err();
+        assert!(matches!(err, MarketplaceError::InvalidExpiry));
+
+        let request_id = suite.try_create_request(&buyer, Some(now + 100)).unwrap();
+        let offer_id = suite
+            .try_create_offer(&seller, request_id, 100, Some(now + 50))
+            .unwrap();
+
+        suite.advance(50);
+        let offers = suite
+            .marketplace
+            .offers_by_request(&suite.app.wrap(), request_id)
+            .unwrap();
+        assert!(offers.offers.is_empty());
+        let offers = suite
+            .marketplace
+            .seller_offers(&suite.app.wrap(), seller.to_string())
+            .unwrap();
+        assert!(offers.offers.is_empty());
+        let err = suite.accept(&buyer, offer_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::OfferExpired));
+
+        suite.advance(50);
+        let listed = suite.marketplace.all_requests(&suite.app.wrap()).unwrap();
+        assert!(listed.requests.is_empty());
+        let listed = suite
+            .marketplace
+            .user_requests(&suite.app.wrap(), buyer.to_string())
+            .unwrap();
+        assert!(listed.requests.is_empty());
+        let err = suite
+            .try_create_offer(&seller, request_id, 100, None)
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestExpired));
+    }
+}
+
+mod installments {
+    use super::*;
+    use crate::state::{Installment, PaymentSchedule};
+
+    /// Price 1_000: a 200 deposit, then 300 and 500 due 1_000 and 2_000
+    /// seconds after the deposit.
+    fn installment_request(suite: &mut Suite) -> u64 {
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let request_id = suite.create_request();
+        let schedule = PaymentSchedule {
+            deposit: 200,
+            installments: vec![
+                Installment {
+                    amount: 300,
+                    due_after: 1_000,
+                },
+                Installment {
+                    amount: 500,
+                    due_after: 2_000,
+                },
+            ],
+        };
+        let msg = suite
+            .marketplace
+            .create_offer(
+                request_id,
+                1_000,
+                vec![],
+                "Store",
+                None,
+                Some(schedule),
+                vec![],
+            )
+            .unwrap();
+        let res = suite.execute(&seller, msg).unwrap();
+        let offer_id = event_id(&res, "marketplace/offer_created", "offer_id");
+        suite.accept(&buyer, offer_id).unwrap();
+        suite.advance(TIME_TO_LOCK);
+        request_id
+    }
+
+    #[test]
+    fn installments_add_up_to_the_price() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let marketplace = suite.marketplace.addr();
+        let request_id = installment_request(&mut suite);
+
+        let err = suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(199, COIN_DENOM),
+            )
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::InsufficientFunds));
+
+        let res = suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(200, COIN_DENOM),
+            )
+            .unwrap();
+        let deposit_at = suite.now();
+        assert_eq!(
+            event_id(&res, "marketplace/installment_paid", "next_due_at"),
+            deposit_at + 1_000
+        );
+        assert_eq!(suite.lifecycle(request_id), RequestLifecycle::PartiallyPaid);
+
+        suite.approve_usdt(&buyer, 300);
+        let err = suite
+            .pay(&buyer, request_id, CoinPayment::USDT, vec![])
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::PaymentCoinMismatch));
+
+        let err = suite.complete(&buyer, request_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestNotAccepted));
+
+        suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(300, COIN_DENOM),
+            )
+            .unwrap();
+        assert_eq!(suite.lifecycle(request_id), RequestLifecycle::PartiallyPaid);
+        let res = suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(500, COIN_DENOM),
+            )
+            .unwrap();
+        assert_eq!(event_id(&res, "marketplace/request_paid", "amount"), 1_000);
+        assert_eq!(suite.lifecycle(request_id), RequestLifecycle::Paid);
+        assert_eq!(suite.native_balance(&marketplace), 1_000);
+
+        let err = suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(500, COIN_DENOM),
+            )
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestAlreadyPaid));
+
+        suite.advance(TIME_TO_LOCK);
+        suite.complete(&buyer, request_id).unwrap();
+        assert_eq!(suite.native_balance(&marketplace), 0);
+        assert_eq!(suite.native_balance(&seller), INITIAL_BALANCE + 1_000);
+
+        let history = suite
+            .marketplace
+            .user_payment_history(&suite.app.wrap(), buyer.to_string())
+            .unwrap();
+        assert_eq!(history.payments[0].installments_paid, 2);
+        assert_eq!(history.payments[0].next_due_at, None);
+    }
+
+    #[test]
+    fn missed_installment_forfeits_the_deposit() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let marketplace = suite.marketplace.addr();
+        let request_id = installment_request(&mut suite);
+        let forfeit = suite.marketplace.forfeit_deposit(request_id).unwrap();
+
+        let err = suite.execute(&seller, forfeit.clone()).unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestNotPartiallyPaid));
+
+        suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(200, COIN_DENOM),
+            )
+            .unwrap();
+        suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(300, COIN_DENOM),
+            )
+            .unwrap();
+
+        suite.advance(2_000);
+        let err = suite.execute(&seller, forfeit.clone()).unwrap_err();
+        assert!(matches!(err, MarketplaceError::InstallmentNotOverdue));
+
+        suite.advance(1);
+        let err = suite.execute(&buyer, forfeit.clone()).unwrap_err();
+        assert!(matches!(err, MarketplaceError::UnauthorizedSeller));
+
+        let res = suite.execute(&seller, forfeit.clone()).unwrap();
+        assert_eq!(
+            event_id(&res, "marketplace/deposit_forfeited", "refunded"),
+            300
+        );
+        assert_eq!(suite.lifecycle(request_id), RequestLifecycle::Defaulted);
+        assert_eq!(suite.native_balance(&marketplace), 0);
+        assert_eq!(suite.native_balance(&seller), INITIAL_BALANCE + 200);
+        assert_eq!(suite.native_balance(&buyer), INITIAL_BALANCE - 200);
+
+        let err = suite.execute(&seller, forfeit).unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestNotPartiallyPaid));
+        let err = suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(500, COIN_DENOM),
+            )
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestNotAccepted));
+    }
+}
+
+mod pricing {
+    use super::*;
+
+    #[test]
+    fn prices_are_converted_at_payment_time() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let marketplace = suite.marketplace.addr();
+        let oracle = suite.use_oracle();
+        // 1 uosmo is worth 0.5 micro-USD, so a 1_000 price costs 2_000 uosmo.
+        suite.set_price(&oracle, COIN_DENOM, "0.5");
+
+        let request_id = suite.accepted_request(1_000);
+        let quote = suite
+            .marketplace
+            .payment_quote(&suite.app.wrap(), request_id, CoinPayment::Cosmos)
+            .unwrap();
+        assert_eq!(quote.price, Uint128::new(1_000));
+        assert_eq!(quote.amount, Uint128::new(2_000));
+
+        // The price drops before the buyer's transaction lands.
+        suite.set_price(&oracle, COIN_DENOM, "0.4");
+        let msg = suite
+            .marketplace
+            .pay_for_request(
+                request_id,
+                CoinPayment::Cosmos,
+                Some(quote.amount),
+                None,
+                coins(2_500, COIN_DENOM),
+            )
+            .unwrap();
+        let err = suite.execute(&buyer, msg).unwrap_err();
+        assert!(matches!(
+            err,
+            MarketplaceError::SlippageExceeded { amount, max_amount }
+                if amount.u128() == 2_500 && max_amount == quote.amount
+        ));
+
+        suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(2_600, COIN_DENOM),
+            )
+            .unwrap();
+        assert_eq!(suite.native_balance(&marketplace), 2_500);
+        assert_eq!(suite.native_balance(&buyer), INITIAL_BALANCE - 2_500);
+        let history = suite
+            .marketplace
+            .user_payment_history(&suite.app.wrap(), buyer.to_string())
+            .unwrap();
+        assert_eq!(history.payments[0].amount, Uint128::new(2_500));
+        assert_eq!(history.payments[0].price_paid, Uint128::new(1_000));
+
+        suite.advance(TIME_TO_LOCK);
+        suite.complete(&buyer, request_id).unwrap();
+        assert_eq!(suite.native_balance(&seller), INITIAL_BALANCE + 2_500);
+    }
+
+    #[test]
+    fn cw20_prices_round_up() {
+        let mut suite = Suite::new();
+        let buyer = suite.buyer.clone();
+        let marketplace = suite.marketplace.addr();
+        let oracle = suite.use_oracle();
+        let usdt = suite.usdt.to_string();
+        suite.set_price(&oracle, &usdt, "3");
+
+        let request_id = suite.accepted_request(1_000);
+        suite.approve_usdt(&buyer, 1_000);
+        suite
+            .pay(&buyer, request_id, CoinPayment::USDT, vec![])
+            .unwrap();
+        assert_eq!(suite.usdt_balance(&marketplace), 334);
+    }
+
+    #[test]
+    fn unusable_prices_are_rejected() {
+        let mut suite = Suite::new();
+        let buyer = suite.buyer.clone();
+        let oracle = suite.use_oracle();
+        let request_id = suite.accepted_request(1_000);
+
+        // No price published for the denom at all.
+        let err = suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(1_000, COIN_DENOM),
+            )
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::Std(_)));
+
+        suite.set_price(&oracle, COIN_DENOM, "0");
+        let err = suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(1_000, COIN_DENOM),
+            )
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::InvalidOraclePrice));
+    }
+}
+
+mod line_items {
+    use super::*;
+    use crate::state::{Installment, LineItem, LineQuote, PaymentSchedule};
+
+    fn grocery_list(suite: &mut Suite) -> u64 {
+        let item = |name: &str, quantity: u64, unit: &str| LineItem {
+            name: name.to_string(),
+            quantity,
+            unit: unit.to_string(),
+        };
+        let msg = suite
+            .marketplace
+            .create_request(
+                "Groceries",
+                "Weekly shopping",
+                vec![],
+                6_524_379,
+                3_379_206,
+                None,
+                None,
+                vec![
+                    item("Rice", 5, "kg"),
+                    item("Eggs", 12, "pcs"),
+                    item("Milk", 2, "l"),
+                ],
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        let buyer = suite.buyer.clone();
+        let res = suite.execute(&buyer, msg).unwrap();
+        event_id(&res, "marketplace/request_created", "request_id")
+    }
+
+    fn quote(line: u32, quantity: u64, unit_price: u128) -> LineQuote {
+        LineQuote {
+            line,
+            quantity,
+            unit_price,
+        }
+    }
+
+    fn try_quote(
+        suite: &mut Suite,
+        request_id: u64,
+        price: u128,
+        line_quotes: Vec<LineQuote>,
+    ) -> Result<u64, MarketplaceError> {
+        let msg = suite
+            .marketplace
+            .create_offer(request_id, price, vec![], "Store", None, None, line_quotes)
+            .unwrap();
+        let seller = suite.seller.clone();
+        let res = suite.execute(&seller, msg)?;
+        Ok(event_id(&res, "marketplace/offer_created", "offer_id"))
+    }
+
+    #[test]
+    fn quotes_must_match_requested_lines() {
+        let mut suite = Suite::new();
+        let request_id = grocery_list(&mut suite);
+
+        let err = try_quote(&mut suite, request_id, 100, vec![quote(0, 5, 10)]).unwrap_err();
+        assert!(matches!(err, MarketplaceError::PriceMismatch));
+
+        let err = try_quote(&mut suite, request_id, 60, vec![quote(0, 6, 10)]).unwrap_err();
+        assert!(matches!(
+            err,
+            MarketplaceError::InvalidLineQuote { line: 0 }
+        ));
+
+        let err = try_quote(&mut suite, request_id, 10, vec![quote(3, 1, 10)]).unwrap_err();
+        assert!(matches!(
+            err,
+            MarketplaceError::InvalidLineQuote { line: 3 }
+        ));
+
+        // Only part of the eggs, no milk at all.
+        let offer_id = try_quote(
+            &mut suite,
+            request_id,
+            50 + 6 * 3,
+            vec![quote(0, 5, 10), quote(1, 6, 3)],
+        )
+        .unwrap();
+        let offer = suite
+            .marketplace
+            .offer(&suite.app.wrap(), offer_id)
+            .unwrap()
+            .offer;
+        assert_eq!(offer.price, 68);
+        assert_eq!(offer.line_quotes.len(), 2);
+    }
+
+    #[test]
+    fn buyer_pays_for_accepted_lines_only() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let marketplace = suite.marketplace.addr();
+        let request_id = grocery_list(&mut suite);
+        let offer_id = try_quote(
+            &mut suite,
+            request_id,
+            50 + 36 + 8,
+            vec![quote(0, 5, 10), quote(1, 12, 3), quote(2, 2, 4)],
+        )
+        .unwrap();
+
+        let msg = suite
+            .marketplace
+            .accept_offer(offer_id, Some(vec![2, 7]))
+            .unwrap();
+        let err = suite.execute(&buyer, msg).unwrap_err();
+        assert!(matches!(err, MarketplaceError::LineNotQuoted { line: 7 }));
+
+        let msg = suite
+            .marketplace
+            .accept_offer(offer_id, Some(vec![2, 0, 2]))
+            .unwrap();
+        suite.execute(&buyer, msg).unwrap();
+        let request = suite
+            .marketplace
+            .request(&suite.app.wrap(), request_id)
+            .unwrap()
+            .request;
+        assert_eq!(request.seller_price_quote, 58);
+        let offer = suite
+            .marketplace
+            .offer(&suite.app.wrap(), offer_id)
+            .unwrap()
+            .offer;
+        assert_eq!(offer.accepted_lines, vec![0, 2]);
+        assert_eq!(offer.accepted_price(), 58);
+
+        suite.advance(TIME_TO_LOCK);
+        let quote = suite
+            .marketplace
+            .payment_quote(&suite.app.wrap(), request_id, CoinPayment::Cosmos)
+            .unwrap();
+        assert_eq!(quote.amount, Uint128::new(58));
+        suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(94, COIN_DENOM),
+            )
+            .unwrap();
+        assert_eq!(suite.native_balance(&marketplace), 58);
+
+        suite.advance(TIME_TO_LOCK);
+        suite.complete(&buyer, request_id).unwrap();
+        assert_eq!(suite.native_balance(&seller), INITIAL_BALANCE + 58);
+    }
+
+    #[test]
+    fn scheduled_offers_are_accepted_in_full() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let request_id = grocery_list(&mut suite);
+        let schedule = PaymentSchedule {
+            deposit: 40,
+            installments: vec![Installment {
+                amount: 46,
+                due_after: 1_000,
+            }],
+        };
+        let msg = suite
+            .marketplace
+            .create_offer(
+                request_id,
+                86,
+                vec![],
+                "Store",
+                None,
+                Some(schedule),
+                vec![quote(0, 5, 10), quote(1, 12, 3)],
+            )
+            .unwrap();
+        let res = suite.execute(&seller, msg).unwrap();
+        let offer_id = event_id(&res, "marketplace/offer_created", "offer_id");
+
+        let msg = suite
+            .marketplace
+            .accept_offer(offer_id, Some(vec![0]))
+            .unwrap();
+        let err = suite.execute(&buyer, msg).unwrap_err();
+        assert!(matches!(
+            err,
+            MarketplaceError::PartialAcceptanceWithSchedule
+        ));
+
+        // Picking every quoted line is a full acceptance.
+        let msg = suite
+            .marketplace
+            .accept_offer(offer_id, Some(vec![1, 0]))
+            .unwrap();
+        suite.execute(&buyer, msg).unwrap();
+        let offer = suite
+            .marketplace
+            .offer(&suite.app.wrap(), offer_id)
+            .unwrap()
+            .offer;
+        assert!(offer.accepted_lines.is_empty());
+        assert_eq!(offer.accepted_price(), 86);
+    }
+}
+
+mod budgets {
+    use super::*;
+    use crate::state::Budget;
+
+    fn request_with_budget(suite: &mut Suite, max_price: u128, coin: Option<CoinPayment>) -> u64 {
+        let msg = suite
+            .marketplace
+            .create_request(
+                "Rice",
+                "50kg bag",
+                vec![],
+                6_524_379,
+                3_379_206,
+                None,
+                None,
+                vec![],
+                Some(Budget { max_price, coin }),
+                None,
+                None,
+            )
+            .unwrap();
+        let buyer = suite.buyer.clone();
+        let res = suite.execute(&buyer, msg).unwrap();
+        event_id(&res, "marketplace/request_created", "request_id")
+    }
+
+    #[test]
+    fn offers_above_budget_are_rejected() {
+        let mut suite = Suite::new();
+        let seller = suite.seller.clone();
+        let request_id = request_with_budget(&mut suite, 500, None);
+
+        let err = suite
+            .try_create_offer(&seller, request_id, 501, None)
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::OfferAboveBudget));
+        suite
+            .try_create_offer(&seller, request_id, 500, None)
+            .unwrap();
+    }
+
+    #[test]
+    fn coin_budgets_are_converted() {
+        let mut suite = Suite::new();
+        let seller = suite.seller.clone();
+        let oracle = suite.use_oracle();
+        suite.set_price(&oracle, COIN_DENOM, "0.5");
+        // 1_000 uosmo buys at most 500 micro-USD.
+        let request_id = request_with_budget(&mut suite, 1_000, Some(CoinPayment::Cosmos));
+
+        let err = suite
+            .try_create_offer(&seller, request_id, 501, None)
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::OfferAboveBudget));
+        suite
+            .try_create_offer(&seller, request_id, 500, None)
+            .unwrap();
+    }
+
+    #[test]
+    fn open_requests_are_listed_by_budget() {
+        let mut suite = Suite::new();
+        let buyer = suite.buyer.clone();
+        let cheap = request_with_budget(&mut suite, 100, None);
+        let mid = request_with_budget(&mut suite, 500, None);
+        let pricey = request_with_budget(&mut suite, 1_000, None);
+        let native = request_with_budget(&mut suite, 500, Some(CoinPayment::Cosmos));
+        let cancelled = request_with_budget(&mut suite, 500, None);
+        suite.create_request();
+
+        let msg = suite.marketplace.cancel_request(cancelled).unwrap();
+        suite.execute(&buyer, msg).unwrap();
+
+        let ids = |suite: &Suite, min, max, coin| -> Vec<u64> {
+            suite
+                .marketplace
+                .requests_by_budget(&suite.app.wrap(), min, max, coin)
+                .unwrap()
+                .requests
+                .iter()
+                .map(|request| request.id)
+                .collect()
+        };
+        assert_eq!(ids(&suite, None, None, None), vec![cheap, mid, pricey]);
+        assert_eq!(ids(&suite, Some(100), Some(500), None), vec![cheap, mid]);
+        assert_eq!(ids(&suite, Some(101), None, None), vec![mid, pricey]);
+        assert_eq!(
+            ids(&suite, None, None, Some(CoinPayment::Cosmos)),
+            vec![native]
+        );
+
+        // Accepted requests are no longer open.
+        let offer_id = suite.create_offer(mid, 400);
+        suite.accept(&buyer, offer_id).unwrap();
+        assert_eq!(ids(&suite, None, None, None), vec![cheap, pricey]);
+    }
+}
+
+mod auctions {
+    use super::*;
+    use crate::helpers::bid_commitment;
+    use crate::state::SealedAuction;
+
+    const BIDDING: u64 = 100;
+    const REVEAL: u64 = 100;
+
+    fn try_auction_request(
+        suite: &mut Suite,
+        auction: SealedAuction,
+        expires_at: Option<u64>,
+    ) -> Result<u64, MarketplaceError> {
+        let msg = suite
+            .marketplace
+            .create_request(
+                "Rice",
+                "50kg bag",
+                vec![],
+                6_524_379,
+                3_379_206,
+                expires_at,
+                None,
+                vec![],
+                None,
+                Some(auction),
+                None,
+            )
+            .unwrap();
+        let buyer = suite.buyer.clone();
+        let res = suite.execute(&buyer, msg)?;
+        Ok(event_id(&res, "marketplace/request_created", "request_id"))
+    }
+
+    fn auction_request(suite: &mut Suite, auto_select: bool) -> u64 {
+        let now = suite.now();
+        let auction = SealedAuction {
+            bidding_ends_at: now + BIDDING,
+            reveal_ends_at: now + BIDDING + REVEAL,
+            auto_select,
+        };
+        try_auction_request(suite, auction, None).unwrap()
+    }
+
+    fn commit(suite: &mut Suite, seller: &Addr, request_id: u64, price: u128) -> u64 {
+        let commitment = bid_commitment(request_id, seller.as_str(), price, "salt");
+        let msg = suite
+            .marketplace
+            .commit_offer(request_id, commitment, vec![], "Store")
+            .unwrap();
+        let res = suite.execute(seller, msg).unwrap();
+        event_id(&res, "marketplace/offer_committed", "offer_id")
+    }
+
+    fn reveal(
+        suite: &mut Suite,
+        seller: &Addr,
+        offer_id: u64,
+        price: u128,
+        salt: &str,
+    ) -> Result<AppResponse, MarketplaceError> {
+        let msg = suite
+            .marketplace
+            .reveal_offer(offer_id, price, salt)
+            .unwrap();
+        suite.execute(seller, msg)
+    }
+
+    fn settle(suite: &mut Suite, request_id: u64) -> Result<AppResponse, MarketplaceError> {
+        let msg = suite.marketplace.settle_auction(request_id).unwrap();
+        let admin = suite.admin.clone();
+        suite.execute(&admin, msg)
+    }
+
+    #[test]
+    fn lowest_revealed_bid_wins() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let second = suite.user("second_seller", AccountType::Seller);
+        let silent = suite.user("silent_seller", AccountType::Seller);
+        let request_id = auction_request(&mut suite, true);
+
+        let err = suite
+            .try_create_offer(&seller, request_id, 500, None)
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::SealedBidsOnly));
+
+        let high = commit(&mut suite, &seller, request_id, 500);
+        let low = commit(&mut suite, &second, request_id, 450);
+        commit(&mut suite, &silent, request_id, 1);
+        let offer = suite
+            .marketplace
+            .offer(&suite.app.wrap(), low)
+            .unwrap()
+            .offer;
+        assert_eq!(offer.price, 0);
+        assert!(offer.commitment.is_some());
+
+        let err = reveal(&mut suite, &second, low, 450, "salt").unwrap_err();
+        assert!(matches!(err, MarketplaceError::NotInRevealWindow));
+
+        suite.advance(BIDDING);
+        let msg = suite
+            .marketplace
+            .commit_offer(
+                request_id,
+                bid_commitment(request_id, "x", 1, "x"),
+                vec![],
+                "Store",
+            )
+            .unwrap();
+        let err = suite.execute(&seller, msg).unwrap_err();
+        assert!(matches!(err, MarketplaceError::BiddingClosed));
+
+        let err = reveal(&mut suite, &second, low, 400, "salt").unwrap_err();
+        assert!(matches!(err, MarketplaceError::CommitmentMismatch));
+        let err = reveal(&mut suite, &seller, low, 450, "salt").unwrap_err();
+        assert!(matches!(err, MarketplaceError::UnauthorizedSeller));
+
+        reveal(&mut suite, &seller, high, 500, "salt").unwrap();
+        let res = reveal(&mut suite, &second, low, 450, "salt").unwrap();
+        assert_eq!(event_id(&res, "marketplace/offer_revealed", "price"), 450);
+        let err = reveal(&mut suite, &second, low, 450, "salt").unwrap_err();
+        assert!(matches!(err, MarketplaceError::OfferNotSealed));
+
+        let err = settle(&mut suite, request_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::AuctionNotEnded));
+
+        suite.advance(REVEAL);
+        let err = suite.accept(&buyer, low).unwrap_err();
+        assert!(matches!(err, MarketplaceError::AutoSelectAuction));
+
+        let res = settle(&mut suite, request_id).unwrap();
+        assert_eq!(
+            event_id(&res, "marketplace/offer_accepted", "offer_id"),
+            low
+        );
+        let request = suite
+            .marketplace
+            .request(&suite.app.wrap(), request_id)
+            .unwrap()
+            .request;
+        assert_eq!(request.lifecycle, RequestLifecycle::AcceptedByBuyer);
+        assert_eq!(request.accepted_offer_id, low);
+        assert_eq!(request.seller_price_quote, 450);
+
+        let err = settle(&mut suite, request_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestLocked));
+    }
+
+    #[test]
+    fn buyer_picks_among_revealed_bids() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let second = suite.user("second_seller", AccountType::Seller);
+        let request_id = auction_request(&mut suite, false);
+
+        let revealed = commit(&mut suite, &seller, request_id, 500);
+        let hidden = commit(&mut suite, &second, request_id, 450);
+        suite.advance(BIDDING);
+        reveal(&mut suite, &seller, revealed, 500, "salt").unwrap();
+
+        let err = suite.accept(&buyer, revealed).unwrap_err();
+        assert!(matches!(err, MarketplaceError::AuctionNotEnded));
+
+        suite.advance(REVEAL);
+        let err = reveal(&mut suite, &second, hidden, 450, "salt").unwrap_err();
+        assert!(matches!(err, MarketplaceError::NotInRevealWindow));
+        let err = suite.accept(&buyer, hidden).unwrap_err();
+        assert!(matches!(err, MarketplaceError::OfferNotRevealed));
+        let err = settle(&mut suite, request_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::ManualSelectAuction));
+
+        suite.accept(&buyer, revealed).unwrap();
+        assert_eq!(
+            suite.lifecycle(request_id),
+            RequestLifecycle::AcceptedByBuyer
+        );
+    }
+
+    #[test]
+    fn auctions_without_bids_or_with_bad_windows() {
+        let mut suite = Suite::new();
+        let now = suite.now();
+        let windows = [(0, 10), (10, 10), (20, 10)];
+        for (bidding, reveal) in windows {
+            let auction = SealedAuction {
+                bidding_ends_at: now + bidding,
+                reveal_ends_at: now + reveal,
+                auto_select: true,
+            };
+            let err = try_auction_request(&mut suite, auction, None).unwrap_err();
+            assert!(matches!(err, MarketplaceError::InvalidAuctionWindow));
+        }
+
+        let request_id = auction_request(&mut suite, true);
+        let seller = suite.seller.clone();
+        commit(&mut suite, &seller, request_id, 500);
+        suite.advance(BIDDING + REVEAL);
+        let err = settle(&mut suite, request_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::NoRevealedOffers));
+
+        let plain = suite.create_request();
+        let err = settle(&mut suite, plain).unwrap_err();
+        assert!(matches!(err, MarketplaceError::NotAnAuction));
+    }
+
+    #[test]
+    fn auctions_must_be_settled_before_the_request_expires() {
+        let mut suite = Suite::new();
+        let seller = suite.seller.clone();
+        let now = suite.now();
+        let auction = SealedAuction {
+            bidding_ends_at: now + BIDDING,
+            reveal_ends_at: now + BIDDING + REVEAL,
+            auto_select: true,
+        };
+
+        // Expiring as the reveal window closes would leave no time to settle.
+        let err = try_auction_request(&mut suite, auction.clone(), Some(auction.reveal_ends_at))
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::InvalidAuctionWindow));
+
+        let expires_at = auction.reveal_ends_at + 10;
+        let request_id = try_auction_request(&mut suite, auction, Some(expires_at)).unwrap();
+        let offer_id = commit(&mut suite, &seller, request_id, 500);
+        suite.advance(BIDDING);
+        reveal(&mut suite, &seller, offer_id, 500, "salt").unwrap();
+
+        suite.advance(REVEAL + 10);
+        let err = settle(&mut suite, request_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestExpired));
+    }
+
+    #[test]
+    fn suspended_bidders_cannot_reveal_or_win() {
+        let mut suite = Suite::new();
+        let (admin, seller) = (suite.admin.clone(), suite.seller.clone());
+        let second = suite.user("second_seller", AccountType::Seller);
+        let sealed = suite.user("sealed_seller", AccountType::Seller);
+        let request_id = auction_request(&mut suite, true);
+
+        let low = commit(&mut suite, &seller, request_id, 400);
+        let high = commit(&mut suite, &second, request_id, 500);
+        let lowest = commit(&mut suite, &sealed, request_id, 300);
+        suite.advance(BIDDING);
+        reveal(&mut suite, &seller, low, 400, "salt").unwrap();
+        reveal(&mut suite, &second, high, 500, "salt").unwrap();
+
+        for user in [&seller, &sealed] {
+            let msg = suite.marketplace.suspend_user(user).unwrap();
+            suite.execute(&admin, msg).unwrap();
+        }
+        let err = reveal(&mut suite, &sealed, lowest, 300, "salt").unwrap_err();
+        assert!(matches!(err, MarketplaceError::Suspended));
+
+        suite.advance(REVEAL);
+        let res = settle(&mut suite, request_id).unwrap();
+        assert_eq!(
+            event_id(&res, "marketplace/offer_accepted", "offer_id"),
+            high
+        );
+    }
+}
+
+mod auto_accept {
+    use super::*;
+    use crate::state::{AcceptStrategy, AutoAccept};
+
+    const DEADLINE: u64 = 1_000;
+
+    fn try_auto_request(
+        suite: &mut Suite,
+        expires_at: Option<u64>,
+        auto_accept: AutoAccept,
+    ) -> Result<u64, MarketplaceError> {
+        let msg = suite
+            .marketplace
+            .create_request(
+                "Rice",
+                "50kg bag",
+                vec![],
+                6_524_379,
+                3_379_206,
+                expires_at,
+                None,
+                vec![],
+                None,
+                None,
+                Some(auto_accept),
+            )
+            .unwrap();
+        let buyer = suite.buyer.clone();
+        let res = suite.execute(&buyer, msg)?;
+        Ok(event_id(&res, "marketplace/request_created", "request_id"))
+    }
+
+    fn auto_request(suite: &mut Suite, strategy: AcceptStrategy) -> u64 {
+        let deadline = suite.now() + DEADLINE;
+        try_auto_request(suite, None, AutoAccept { deadline, strategy }).unwrap()
+    }
+
+    fn finalize(suite: &mut Suite, request_id: u64) -> Result<AppResponse, MarketplaceError> {
+        let msg = suite.marketplace.finalize_request(request_id).unwrap();
+        let admin = suite.admin.clone();
+        suite.execute(&admin, msg)
+    }
+
+    #[test]
+    fn lowest_price_is_accepted_after_deadline() {
+        let mut suite = Suite::new();
+        let second = suite.user("second_seller", AccountType::Seller);
+        let third = suite.user("third_seller", AccountType::Seller);
+        let request_id = auto_request(&mut suite, AcceptStrategy::LowestPrice);
+
+        suite.create_offer(request_id, 500);
+        let cheap = suite
+            .try_create_offer(&second, request_id, 450, None)
+            .unwrap();
+        let expiring = suite
+            .try_create_offer(&third, request_id, 100, Some(suite.now() + 10))
+            .unwrap();
+
+        let err = finalize(&mut suite, request_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::DeadlineNotReached));
+
+        suite.advance(DEADLINE);
+        let res = finalize(&mut suite, request_id).unwrap();
+        assert_eq!(
+            event_id(&res, "marketplace/offer_accepted", "offer_id"),
+            cheap
+        );
+        let request = suite
+            .marketplace
+            .request(&suite.app.wrap(), request_id)
+            .unwrap()
+            .request;
+        assert_eq!(request.lifecycle, RequestLifecycle::AcceptedByBuyer);
+        assert_eq!(request.accepted_offer_id, cheap);
+        assert_ne!(request.accepted_offer_id, expiring);
+        assert_eq!(request.seller_price_quote, 450);
+
+        let err = finalize(&mut suite, request_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestLocked));
+    }
+
+    #[test]
+    fn suspended_sellers_are_skipped() {
+        let mut suite = Suite::new();
+        let (admin, seller) = (suite.admin.clone(), suite.seller.clone());
+        let second = suite.user("second_seller", AccountType::Seller);
+        let request_id = auto_request(&mut suite, AcceptStrategy::LowestPrice);
+
+        let fair = suite
+            .try_create_offer(&second, request_id, 500, None)
+            .unwrap();
+        suite.create_offer(request_id, 100);
+        let msg = suite.marketplace.suspend_user(&seller).unwrap();
+        suite.execute(&admin, msg).unwrap();
+
+        suite.advance(DEADLINE);
+        let res = finalize(&mut suite, request_id).unwrap();
+        assert_eq!(
+            event_id(&res, "marketplace/offer_accepted", "offer_id"),
+            fair
+        );
+    }
+
+    #[test]
+    fn suspended_buyers_requests_are_not_finalized() {
+        let mut suite = Suite::new();
+        let (admin, buyer) = (suite.admin.clone(), suite.buyer.clone());
+        let request_id = auto_request(&mut suite, AcceptStrategy::LowestPrice);
+        suite.create_offer(request_id, 500);
+
+        let msg = suite.marketplace.suspend_user(&buyer).unwrap();
+        suite.execute(&admin, msg).unwrap();
+        suite.advance(DEADLINE);
+        let err = finalize(&mut suite, request_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Suspended));
+        assert_eq!(
+            suite.lifecycle(request_id),
+            RequestLifecycle::AcceptedBySeller
+        );
+
+        let msg = suite.marketplace.unsuspend_user(&buyer).unwrap();
+        suite.execute(&admin, msg).unwrap();
+        finalize(&mut suite, request_id).unwrap();
+    }
+
+    #[test]
+    fn highest_rated_seller_wins_over_price() {
+        let mut suite = Suite::new();
+        let buyer = suite.buyer.clone();
+        let newcomer = suite.user("new_seller", AccountType::Seller);
+
+        let sold = suite.accepted_request(300);
+        suite
+            .pay(&buyer, sold, CoinPayment::Cosmos, coins(300, COIN_DENOM))
+            .unwrap();
+        suite.advance(TIME_TO_LOCK);
+        suite.complete(&buyer, sold).unwrap();
+        let seller = suite
+            .marketplace
+            .user(&suite.app.wrap(), suite.seller.to_string())
+            .unwrap();
+        assert_eq!(seller.completed_sales, 1);
+
+        let request_id = auto_request(&mut suite, AcceptStrategy::HighestRatedSeller);
+        suite
+            .try_create_offer(&newcomer, request_id, 400, None)
+            .unwrap();
+        let rated = suite.create_offer(request_id, 500);
+
+        suite.advance(DEADLINE);
+        let res = finalize(&mut suite, request_id).unwrap();
+        assert_eq!(
+            event_id(&res, "marketplace/offer_accepted", "offer_id"),
+            rated
+        );
+    }
+
+    #[test]
+    fn buyer_may_accept_before_deadline() {
+        let mut suite = Suite::new();
+        let buyer = suite.buyer.clone();
+        let request_id = auto_request(&mut suite, AcceptStrategy::LowestPrice);
+        let offer_id = suite.create_offer(request_id, 500);
+
+        suite.accept(&buyer, offer_id).unwrap();
+        suite.advance(DEADLINE);
+        let err = finalize(&mut suite, request_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestLocked));
+    }
+
+    #[test]
+    fn invalid_or_missing_auto_accept() {
+        let mut suite = Suite::new();
+        let now = suite.now();
+        let strategy = AcceptStrategy::LowestPrice;
+
+        let past = AutoAccept {
+            deadline: now,
+            strategy: strategy.clone(),
+        };
+        let err = try_auto_request(&mut suite, None, past).unwrap_err();
+        assert!(matches!(err, MarketplaceError::InvalidAutoAccept));
+
+        let after_expiry = AutoAccept {
+            deadline: now + DEADLINE,
+            strategy,
+        };
+        let err = try_auto_request(&mut suite, Some(now + 10), after_expiry).unwrap_err();
+        assert!(matches!(err, MarketplaceError::InvalidAutoAccept));
+
+        let request_id = auto_request(&mut suite, AcceptStrategy::LowestPrice);
+        suite.advance(DEADLINE);
+        let err = finalize(&mut suite, request_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::NoEligibleOffers));
+
+        let plain = suite.create_request();
+        let err = finalize(&mut suite, plain).unwrap_err();
+        assert!(matches!(err, MarketplaceError::AutoAcceptNotEnabled));
+    }
+}
+
+mod negotiation {
+    use super::*;
+    use crate::state::{Installment, PaymentSchedule, RevisionStatus};
+
+    fn counter(
+        suite: &mut Suite,
+        sender: &Addr,
+        offer_id: u64,
+        price: u128,
+    ) -> Result<AppResponse, MarketplaceError> {
+        let msg = suite.marketplace.counter_offer(offer_id, price).unwrap();
+        suite.execute(sender, msg)
+    }
+
+    fn respond(
+        suite: &mut Suite,
+        sender: &Addr,
+        offer_id: u64,
+        accept: bool,
+    ) -> Result<AppResponse, MarketplaceError> {
+        let msg = suite
+            .marketplace
+            .respond_to_counter_offer(offer_id, accept)
+            .unwrap();
+        suite.execute(sender, msg)
+    }
+
+    #[test]
+    fn agreed_counter_price_is_accepted() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let request_id = suite.create_request();
+        let offer_id = suite.create_offer(request_id, 500);
+
+        let err = counter(&mut suite, &seller, offer_id, 480).unwrap_err();
+        assert!(matches!(err, MarketplaceError::NoPendingCounterOffer));
+
+        let res = counter(&mut suite, &buyer, offer_id, 400).unwrap();
+        assert_eq!(event_id(&res, "marketplace/offer_countered", "revision"), 1);
+        let err = counter(&mut suite, &buyer, offer_id, 420).unwrap_err();
+        assert!(matches!(err, MarketplaceError::CounterOfferPending));
+        let err = respond(&mut suite, &buyer, offer_id, true).unwrap_err();
+        assert!(matches!(err, MarketplaceError::CounterOfferPending));
+
+        counter(&mut suite, &seller, offer_id, 450).unwrap();
+        respond(&mut suite, &buyer, offer_id, true).unwrap();
+
+        let offer = suite
+            .marketplace
+            .offer(&suite.app.wrap(), offer_id)
+            .unwrap()
+            .offer;
+        assert_eq!(offer.price, 450);
+        let rounds: Vec<_> = offer
+            .revisions
+            .iter()
+            .map(|revision| (revision.price, revision.status.clone()))
+            .collect();
+        assert_eq!(
+            rounds,
+            vec![
+                (500, RevisionStatus::Accepted),
+                (400, RevisionStatus::Rejected),
+                (450, RevisionStatus::Accepted),
+            ]
+        );
+
+        suite.accept(&buyer, offer_id).unwrap();
+        let request = suite
+            .marketplace
+            .request(&suite.app.wrap(), request_id)
+            .unwrap()
+            .request;
+        assert_eq!(request.seller_price_quote, 450);
+
+        let err = counter(&mut suite, &buyer, offer_id, 300).unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestLocked));
+    }
+
+    #[test]
+    fn accepting_closes_pending_counter_offers() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let other_seller = suite.user("other_seller", AccountType::Seller);
+        let request_id = suite.create_request();
+        let offer_id = suite.create_offer(request_id, 500);
+        let other_id = suite
+            .try_create_offer(&other_seller, request_id, 600, None)
+            .unwrap();
+
+        counter(&mut suite, &buyer, offer_id, 400).unwrap();
+        counter(&mut suite, &buyer, other_id, 450).unwrap();
+        suite.accept(&buyer, offer_id).unwrap();
+
+        for id in [offer_id, other_id] {
+            let offer = suite
+                .marketplace
+                .offer(&suite.app.wrap(), id)
+                .unwrap()
+                .offer;
+            assert!(offer.pending_revision().is_none());
+            assert_eq!(
+                offer.revisions.last().unwrap().status,
+                RevisionStatus::Rejected
+            );
+        }
+        let err = respond(&mut suite, &seller, offer_id, true).unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestLocked));
+
+        // Once the buyer backs out, the old counter-offer stays closed.
+        let msg = suite.marketplace.cancel_request(request_id).unwrap();
+        suite.execute(&buyer, msg).unwrap();
+        let offer = suite
+            .marketplace
+            .offer(&suite.app.wrap(), offer_id)
+            .unwrap()
+            .offer;
+        assert_eq!(offer.price, 500);
+        assert!(offer.pending_revision().is_none());
+    }
+
+    #[test]
+    fn rejected_counter_keeps_the_price() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let stranger = suite.user("stranger", AccountType::Buyer);
+        let request_id = suite.create_request();
+        let offer_id = suite.create_offer(request_id, 500);
+
+        let err = counter(&mut suite, &stranger, offer_id, 100).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Unauthorized));
+
+        counter(&mut suite, &buyer, offer_id, 100).unwrap();
+        let res = respond(&mut suite, &seller, offer_id, false).unwrap();
+        assert_eq!(
+            event_id(&res, "marketplace/counter_offer_answered", "price"),
+            100
+        );
+        let err = respond(&mut suite, &seller, offer_id, true).unwrap_err();
+        assert!(matches!(err, MarketplaceError::NoPendingCounterOffer));
+
+        suite.accept(&buyer, offer_id).unwrap();
+        let request = suite
+            .marketplace
+            .request(&suite.app.wrap(), request_id)
+            .unwrap()
+            .request;
+        assert_eq!(request.seller_price_quote, 500);
+    }
+
+    #[test]
+    fn updated_prices_are_recorded() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let request_id = suite.create_request();
+        let offer_id = suite.create_offer(request_id, 500);
+
+        counter(&mut suite, &buyer, offer_id, 400).unwrap();
+        let msg = suite
+            .marketplace
+            .update_offer(offer_id, 450, vec![], "Store", None, None, vec![])
+            .unwrap();
+        suite.execute(&seller, msg).unwrap();
+
+        let offer = suite
+            .marketplace
+            .offer(&suite.app.wrap(), offer_id)
+            .unwrap()
+            .offer;
+        let rounds: Vec<_> = offer
+            .revisions
+            .iter()
+            .map(|revision| (revision.price, revision.status.clone()))
+            .collect();
+        assert_eq!(
+            rounds,
+            vec![
+                (500, RevisionStatus::Accepted),
+                (400, RevisionStatus::Rejected),
+                (450, RevisionStatus::Accepted),
+            ]
+        );
+        assert_eq!(offer.price, 450);
+    }
+
+    #[test]
+    fn scheduled_offers_cannot_be_negotiated() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let request_id = suite.create_request();
+        let schedule = PaymentSchedule {
+            deposit: 200,
+            installments: vec![Installment {
+                amount: 300,
+                due_after: 1_000,
+            }],
+        };
+        let msg = suite
+            .marketplace
+            .create_offer(
+                request_id,
+                500,
+                vec![],
+                "Store",
+                None,
+                Some(schedule),
+                vec![],
+            )
+            .unwrap();
+        let res = suite.execute(&seller, msg).unwrap();
+        let offer_id = event_id(&res, "marketplace/offer_created", "offer_id");
+
+        let err = counter(&mut suite, &buyer, offer_id, 400).unwrap_err();
+        assert!(matches!(err, MarketplaceError::NotNegotiable));
+    }
+}
+
+mod staking {
+    use super::*;
+
+    const MIN_STAKE: u128 = 1_000;
+    const UNBONDING: u64 = 100;
+
+    fn require_stake(suite: &mut Suite) {
+        let msg = suite
+            .marketplace
+            .update_config(
+                None,
+                None,
+                None,
+                None,
+                Some(Uint128::new(MIN_STAKE)),
+                Some(UNBONDING),
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        let admin = suite.admin.clone();
+        suite.execute(&admin, msg).unwrap();
+    }
+
+    fn bond(suite: &mut Suite, funds: Vec<Coin>) -> Result<AppResponse, MarketplaceError> {
+        let msg = suite.marketplace.bond_stake(funds).unwrap();
+        let seller = suite.seller.clone();
+        suite.execute(&seller, msg)
+    }
+
+    fn unbond(suite: &mut Suite, amount: u128) -> Result<AppResponse, MarketplaceError> {
+        let msg = suite
+            .marketplace
+            .unbond_stake(Uint128::new(amount))
+            .unwrap();
+        let seller = suite.seller.clone();
+        suite.execute(&seller, msg)
+    }
+
+    fn withdraw(suite: &mut Suite) -> Result<AppResponse, MarketplaceError> {
+        let msg = suite.marketplace.withdraw_stake().unwrap();
+        let seller = suite.seller.clone();
+        suite.execute(&seller, msg)
+    }
+
+    fn stake(suite: &Suite) -> SellerStake {
+        suite
+            .marketplace
+            .seller_stake(&suite.app.wrap(), suite.seller.to_string())
+            .unwrap()
+            .stake
+    }
+
+    #[test]
+    fn offers_require_bonded_stake() {
+        let mut suite = Suite::new();
+        let seller = suite.seller.clone();
+        require_stake(&mut suite);
+        let request_id = suite.create_request();
+
+        let err = suite
+            .try_create_offer(&seller, request_id, 500, None)
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::InsufficientStake { .. }));
+
+        let err = bond(&mut suite, coins(MIN_STAKE, OTHER_DENOM)).unwrap_err();
+        assert!(matches!(err, MarketplaceError::UnexpectedFunds { .. }));
+        bond(&mut suite, coins(MIN_STAKE, COIN_DENOM)).unwrap();
+        suite
+            .try_create_offer(&seller, request_id, 500, None)
+            .unwrap();
+
+        unbond(&mut suite, 600).unwrap();
+        let unbonding = stake(&suite);
+        assert_eq!(unbonding.bonded.u128(), 400);
+        assert_eq!(unbonding.unbonding.u128(), 600);
+        assert_eq!(unbonding.unbonding_ends_at, Some(suite.now() + UNBONDING));
+        let err = suite
+            .try_create_offer(&seller, request_id, 500, None)
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::InsufficientStake { .. }));
+        let err = unbond(&mut suite, 401).unwrap_err();
+        assert!(matches!(err, MarketplaceError::InsufficientStake { .. }));
+
+        let err = withdraw(&mut suite).unwrap_err();
+        assert!(matches!(err, MarketplaceError::StakeStillUnbonding));
+        suite.advance(UNBONDING);
+        withdraw(&mut suite).unwrap();
+        assert_eq!(suite.native_balance(&seller), INITIAL_BALANCE - 400);
+        let err = withdraw(&mut suite).unwrap_err();
+        assert!(matches!(err, MarketplaceError::NothingToWithdraw));
+    }
+
+    #[test]
+    fn slashing_refunds_the_buyer() {
+        let mut suite = Suite::new();
+        let buyer = suite.buyer.clone();
+        require_stake(&mut suite);
+        bond(&mut suite, coins(1_500, COIN_DENOM)).unwrap();
+
+        let mut requests = vec![];
+        for _ in 0..2 {
+            let request_id = suite.accepted_request(500);
+            suite
+                .pay(
+                    &buyer,
+                    request_id,
+                    CoinPayment::Cosmos,
+                    coins(500, COIN_DENOM),
+                )
+                .unwrap();
+            requests.push(request_id);
+        }
+        unbond(&mut suite, 500).unwrap();
+
+        let msg = suite.marketplace.slash_stake(requests[0]).unwrap();
+        let err = suite.execute(&buyer, msg.clone()).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Unauthorized));
+
+        let admin = suite.admin.clone();
+        let res = suite.execute(&admin, msg.clone()).unwrap();
+        assert_eq!(
+            event_id(&res, "marketplace/stake_slashed", "slashed"),
+            MIN_STAKE as u64
+        );
+        assert_eq!(
+            suite.native_balance(&buyer),
+            INITIAL_BALANCE - 500 + MIN_STAKE
+        );
+        assert_eq!(suite.lifecycle(requests[0]), RequestLifecycle::Defaulted);
+        let slashed = stake(&suite);
+        assert_eq!(slashed.bonded.u128(), 0);
+        assert_eq!(slashed.unbonding.u128(), 500);
+
+        let err = suite.execute(&admin, msg).unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestNotPaid));
+
+        // Stake that is still unbonding remains slashable.
+        let msg = suite.marketplace.slash_stake(requests[1]).unwrap();
+        let res = suite.execute(&admin, msg).unwrap();
+        assert_eq!(event_id(&res, "marketplace/stake_slashed", "slashed"), 500);
+        let slashed = stake(&suite);
+        assert_eq!((slashed.bonded + slashed.unbonding).u128(), 0);
+    }
+
+    #[test]
+    fn open_escrow_keeps_stake_bonded() {
+        let mut suite = Suite::new();
+        let buyer = suite.buyer.clone();
+        require_stake(&mut suite);
+        bond(&mut suite, coins(1_500, COIN_DENOM)).unwrap();
+
+        let request_id = suite.accepted_request(500);
+        suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(500, COIN_DENOM),
+            )
+            .unwrap();
+
+        let err = unbond(&mut suite, 501).unwrap_err();
+        assert!(matches!(err, MarketplaceError::StakeLocked { .. }));
+        unbond(&mut suite, 500).unwrap();
+        suite.advance(UNBONDING);
+        withdraw(&mut suite).unwrap();
+
+        suite.advance(TIME_TO_LOCK);
+        suite.complete(&buyer, request_id).unwrap();
+        unbond(&mut suite, MIN_STAKE).unwrap();
+    }
+
+    #[test]
+    fn escrow_only_opens_with_bonded_stake() {
+        let mut suite = Suite::new();
+        let buyer = suite.buyer.clone();
+        require_stake(&mut suite);
+        bond(&mut suite, coins(MIN_STAKE, COIN_DENOM)).unwrap();
+
+        let request_id = suite.accepted_request(500);
+        unbond(&mut suite, MIN_STAKE).unwrap();
+        let err = suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(500, COIN_DENOM),
+            )
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::InsufficientStake { .. }));
+
+        bond(&mut suite, coins(MIN_STAKE, COIN_DENOM)).unwrap();
+        suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(500, COIN_DENOM),
+            )
+            .unwrap();
+        suite.advance(UNBONDING);
+        withdraw(&mut suite).unwrap();
+
+        let msg = suite.marketplace.slash_stake(request_id).unwrap();
+        let admin = suite.admin.clone();
+        suite.execute(&admin, msg).unwrap();
+        assert_eq!(suite.native_balance(&buyer), INITIAL_BALANCE + MIN_STAKE);
+        assert_eq!(stake(&suite).bonded, Uint128::zero());
+    }
+}
+
+mod limits {
+    use super::*;
+    use crate::msg::ExecuteMsg;
+
+    const FEE: u128 = 100;
+
+    fn configure(suite: &mut Suite, max_open: u32, max_offers: u32, max_sellers: u32, fee: u128) {
+        let msg = suite
+            .marketplace
+            .update_config(
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(max_open),
+                Some(max_offers),
+                Some(max_sellers),
+                Some(Uint128::new(fee)),
+                None,
+            )
+            .unwrap();
+        let admin = suite.admin.clone();
+        suite.execute(&admin, msg).unwrap();
+    }
+
+    fn try_paid_request(suite: &mut Suite, funds: Vec<Coin>) -> Result<u64, MarketplaceError> {
+        let msg = ExecuteMsg::CreateRequest {
+            name: "Rice".to_string(),
+            description: "50kg bag".to_string(),
+            images: vec![],
+            latitude: 6_524_379,
+            longitude: 3_379_206,
+            expires_at: None,
+            time_to_lock: None,
+            line_items: vec![],
+            budget: None,
+            auction: None,
+            auto_accept: None,
+        };
+        let msg = suite.marketplace.call_with_funds(msg, funds).unwrap();
+        let buyer = suite.buyer.clone();
+        let res = suite.execute(&buyer, msg)?;
+        Ok(event_id(&res, "marketplace/request_created", "request_id"))
+    }
+
+    fn cancel(suite: &mut Suite, request_id: u64) {
+        let msg = suite.marketplace.cancel_request(request_id).unwrap();
+        let buyer = suite.buyer.clone();
+        suite.execute(&buyer, msg).unwrap();
+    }
+
+    #[test]
+    fn open_requests_are_capped_per_buyer() {
+        let mut suite = Suite::new();
+        let buyer = suite.buyer.clone();
+        configure(&mut suite, 2, 0, 0, 0);
+
+        let first = suite.create_request();
+        suite.create_request();
+        let err = suite.try_create_request(&buyer, None).unwrap_err();
+        assert!(matches!(
+            err,
+            MarketplaceError::TooManyOpenRequests { max: 2 }
+        ));
+
+        cancel(&mut suite, first);
+        suite.try_create_request(&buyer, None).unwrap();
+    }
+
+    #[test]
+    fn unpaid_accepted_request_releases_its_slot() {
+        let mut suite = Suite::new();
+        let (buyer, admin) = (suite.buyer.clone(), suite.admin.clone());
+        configure(&mut suite, 1, 0, 0, FEE);
+
+        let request_id = try_paid_request(&mut suite, coins(FEE, COIN_DENOM)).unwrap();
+        let offer_id = suite.create_offer(request_id, 500);
+        suite.accept(&buyer, offer_id).unwrap();
+        suite.advance(TIME_TO_LOCK);
+        let err = try_paid_request(&mut suite, coins(FEE, COIN_DENOM)).unwrap_err();
+        assert!(matches!(err, MarketplaceError::TooManyOpenRequests { .. }));
+
+        cancel(&mut suite, request_id);
+        assert_eq!(suite.lifecycle(request_id), RequestLifecycle::Cancelled);
+        assert_eq!(suite.native_balance(&admin), FEE);
+        let offer = suite
+            .marketplace
+            .offer(&suite.app.wrap(), offer_id)
+            .unwrap()
+            .offer;
+        assert!(offer.is_cancelled);
+        try_paid_request(&mut suite, coins(FEE, COIN_DENOM)).unwrap();
+    }
+
+    #[test]
+    fn one_active_offer_per_seller() {
+        let mut suite = Suite::new();
+        let seller = suite.seller.clone();
+        let request_id = suite.create_request();
+
+        let offer_id = suite
+            .try_create_offer(&seller, request_id, 500, Some(suite.now() + 10))
+            .unwrap();
+        let err = suite
+            .try_create_offer(&seller, request_id, 450, None)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            MarketplaceError::ActiveOfferExists { offer_id: id } if id == offer_id
+        ));
+
+        let msg = suite
+            .marketplace
+            .update_offer(offer_id, 450, vec![], "Store", None, None, vec![])
+            .unwrap();
+        let buyer = suite.buyer.clone();
+        let err = suite.execute(&buyer, msg.clone()).unwrap_err();
+        assert!(matches!(err, MarketplaceError::UnauthorizedSeller));
+        suite.execute(&seller, msg).unwrap();
+        let offer = suite
+            .marketplace
+            .offer(&suite.app.wrap(), offer_id)
+            .unwrap()
+            .offer;
+        assert_eq!((offer.price, offer.expires_at), (450, None));
+
+        // An expired offer no longer blocks a new one.
+        let msg = suite
+            .marketplace
+            .update_offer(
+                offer_id,
+                450,
+                vec![],
+                "Store",
+                Some(suite.now() + 10),
+                None,
+                vec![],
+            )
+            .unwrap();
+        suite.execute(&seller, msg).unwrap();
+        suite.advance(10);
+        suite.create_offer(request_id, 400);
+
+        // ...and cannot be revived next to its replacement.
+        let msg = suite
+            .marketplace
+            .update_offer(offer_id, 450, vec![], "Store", None, None, vec![])
+            .unwrap();
+        let err = suite.execute(&seller, msg).unwrap_err();
+        assert!(matches!(err, MarketplaceError::OfferExpired));
+        let request = suite
+            .marketplace
+            .request(&suite.app.wrap(), request_id)
+            .unwrap()
+            .request;
+        assert_eq!(request.offer_ids.len(), 2);
+        assert_eq!(request.seller_ids.len(), 1);
+    }
+
+    #[test]
+    fn offers_and_sellers_are_capped() {
+        let mut suite = Suite::new();
+        let seller = suite.seller.clone();
+        let second = suite.user("second_seller", AccountType::Seller);
+        let third = suite.user("third_seller", AccountType::Seller);
+        configure(&mut suite, 0, 1, 2, 0);
+        let request_id = suite.create_request();
+
+        suite
+            .try_create_offer(&seller, request_id, 500, Some(suite.now() + 10))
+            .unwrap();
+        suite
+            .try_create_offer(&second, request_id, 450, None)
+            .unwrap();
+        let err = suite
+            .try_create_offer(&third, request_id, 400, None)
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::IncorrectNumberOfSellers));
+
+        suite.advance(10);
+        let err = suite
+            .try_create_offer(&seller, request_id, 450, None)
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::TooManyOffers { max: 1 }));
+    }
+
+    #[test]
+    fn creation_fee_is_refunded_on_completion_only() {
+        let mut suite = Suite::new();
+        let (buyer, admin) = (suite.buyer.clone(), suite.admin.clone());
+        configure(&mut suite, 0, 0, 0, FEE);
+
+        let err = try_paid_request(&mut suite, vec![]).unwrap_err();
+        assert!(matches!(
+            err,
+            MarketplaceError::CreationFeeRequired { fee } if fee.u128() == FEE
+        ));
+        let err = try_paid_request(&mut suite, coins(FEE, OTHER_DENOM)).unwrap_err();
+        assert!(matches!(err, MarketplaceError::UnexpectedFunds { .. }));
+
+        let cancelled = try_paid_request(&mut suite, coins(FEE, COIN_DENOM)).unwrap();
+        cancel(&mut suite, cancelled);
+        assert_eq!(suite.native_balance(&admin), FEE);
+
+        let request_id = try_paid_request(&mut suite, coins(FEE, COIN_DENOM)).unwrap();
+        let offer_id = suite.create_offer(request_id, 500);
+        suite.accept(&buyer, offer_id).unwrap();
+        suite.advance(TIME_TO_LOCK);
+        suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(500, COIN_DENOM),
+            )
+            .unwrap();
+        assert_eq!(
+            suite.native_balance(&buyer),
+            INITIAL_BALANCE - 2 * FEE - 500
+        );
+
+        suite.advance(TIME_TO_LOCK);
+        let res = suite.complete(&buyer, request_id).unwrap();
+        assert_eq!(
+            event_id(&res, "marketplace/creation_fee_released", "amount"),
+            FEE as u64
+        );
+        assert_eq!(suite.native_balance(&buyer), INITIAL_BALANCE - FEE - 500);
+    }
+}
+
+mod delivery {
+    use super::*;
+    use crate::helpers::delivery_code;
+    use crate::state::{DeliveryStage, AUTO_RELEASE_AFTER};
+    use cosmwasm_std::HexBinary;
+
+    fn post(
+        suite: &mut Suite,
+        sender: &Addr,
+        request_id: u64,
+        stage: DeliveryStage,
+        tracking_ref: Option<&str>,
+    ) -> Result<AppResponse, MarketplaceError> {
+        let msg = suite
+            .marketplace
+            .post_delivery_milestone(request_id, stage, tracking_ref.map(str::to_string))
+            .unwrap();
+        suite.execute(sender, msg)
+    }
+
+    fn release(suite: &mut Suite, request_id: u64) -> Result<AppResponse, MarketplaceError> {
+        let msg = suite.marketplace.release_escrow(request_id).unwrap();
+        let admin = suite.admin.clone();
+        suite.execute(&admin, msg)
+    }
+
+    fn paid_request(suite: &mut Suite, price: u128) -> u64 {
+        let buyer = suite.buyer.clone();
+        let request_id = suite.accepted_request(price);
+        suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(price, COIN_DENOM),
+            )
+            .unwrap();
+        request_id
+    }
+
+    #[test]
+    fn milestones_must_advance() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+
+        let request_id = suite.accepted_request(500);
+        let err = post(
+            &mut suite,
+            &seller,
+            request_id,
+            DeliveryStage::Shipped,
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestNotPaid));
+
+        suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(500, COIN_DENOM),
+            )
+            .unwrap();
+        let err = post(&mut suite, &buyer, request_id, DeliveryStage::Shipped, None).unwrap_err();
+        assert!(matches!(err, MarketplaceError::UnauthorizedSeller));
+
+        let long_ref = "x".repeat(65);
+        let err = post(
+            &mut suite,
+            &seller,
+            request_id,
+            DeliveryStage::Shipped,
+            Some(&long_ref),
+        )
+        .unwrap_err();
+        assert!(matches!(err, MarketplaceError::FieldTooLong { .. }));
+
+        post(
+            &mut suite,
+            &seller,
+            request_id,
+            DeliveryStage::OutForDelivery,
+            Some("TRK-1"),
+        )
+        .unwrap();
+        let err = post(
+            &mut suite,
+            &seller,
+            request_id,
+            DeliveryStage::Shipped,
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(err, MarketplaceError::InvalidMilestone));
+        let err = post(
+            &mut suite,
+            &seller,
+            request_id,
+            DeliveryStage::OutForDelivery,
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(err, MarketplaceError::InvalidMilestone));
+
+        let status = suite
+            .marketplace
+            .delivery_status(&suite.app.wrap(), request_id)
+            .unwrap();
+        assert_eq!(status.milestones.len(), 1);
+        assert_eq!(status.milestones[0].stage, DeliveryStage::OutForDelivery);
+        assert_eq!(status.milestones[0].tracking_ref.as_deref(), Some("TRK-1"));
+        assert_eq!(status.milestones[0].at, suite.now());
+        assert_eq!(status.auto_release_at, None);
+    }
+
+    #[test]
+    fn escrow_is_released_after_delivery_window() {
+        let mut suite = Suite::new();
+        let seller = suite.seller.clone();
+        let marketplace = suite.marketplace.addr();
+
+        let request_id = paid_request(&mut suite, 500);
+        let err = release(&mut suite, request_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::ReleaseNotDue));
+
+        post(
+            &mut suite,
+            &seller,
+            request_id,
+            DeliveryStage::Shipped,
+            None,
+        )
+        .unwrap();
+        post(
+            &mut suite,
+            &seller,
+            request_id,
+            DeliveryStage::Delivered,
+            None,
+        )
+        .unwrap();
+        let delivered_at = suite.now();
+        let status = suite
+            .marketplace
+            .delivery_status(&suite.app.wrap(), request_id)
+            .unwrap();
+        assert_eq!(status.milestones.len(), 2);
+        assert_eq!(
+            status.auto_release_at,
+            Some(delivered_at + AUTO_RELEASE_AFTER)
+        );
+
+        suite.advance(AUTO_RELEASE_AFTER - 1);
+        let err = release(&mut suite, request_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::ReleaseNotDue));
+
+        suite.advance(1);
+        let res = release(&mut suite, request_id).unwrap();
+        assert_eq!(
+            event_id(&res, "marketplace/request_completed", "request_id"),
+            request_id
+        );
+        assert_eq!(suite.lifecycle(request_id), RequestLifecycle::Completed);
+        assert_eq!(suite.native_balance(&marketplace), 0);
+        assert_eq!(suite.native_balance(&seller), INITIAL_BALANCE + 500);
+
+        let status = suite
+            .marketplace
+            .delivery_status(&suite.app.wrap(), request_id)
+            .unwrap();
+        assert_eq!(status.auto_release_at, None);
+        let err = release(&mut suite, request_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestNotPaid));
+    }
+
+    #[test]
+    fn buyer_can_complete_before_release() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+
+        let request_id = paid_request(&mut suite, 500);
+        post(
+            &mut suite,
+            &seller,
+            request_id,
+            DeliveryStage::Delivered,
+            None,
+        )
+        .unwrap();
+        suite.advance(TIME_TO_LOCK);
+        suite.complete(&buyer, request_id).unwrap();
+        assert_eq!(suite.native_balance(&seller), INITIAL_BALANCE + 500);
+
+        suite.advance(AUTO_RELEASE_AFTER);
+        let err = release(&mut suite, request_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestNotPaid));
+        let err = post(
+            &mut suite,
+            &seller,
+            request_id,
+            DeliveryStage::Delivered,
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestNotPaid));
+    }
+
+    #[test]
+    fn milestones_do_not_extend_the_lock() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+
+        let request_id = paid_request(&mut suite, 500);
+        let locked_until = suite
+            .marketplace
+            .request(&suite.app.wrap(), request_id)
+            .unwrap()
+            .locked_until;
+        suite.advance(TIME_TO_LOCK - 1);
+        for stage in [DeliveryStage::Shipped, DeliveryStage::Delivered] {
+            post(&mut suite, &seller, request_id, stage, None).unwrap();
+        }
+        let res = suite
+            .marketplace
+            .request(&suite.app.wrap(), request_id)
+            .unwrap();
+        assert_eq!(res.locked_until, locked_until);
+
+        suite.advance(1);
+        suite.complete(&buyer, request_id).unwrap();
+        assert_eq!(suite.native_balance(&seller), INITIAL_BALANCE + 500);
+    }
+
+    fn confirm(
+        suite: &mut Suite,
+        sender: &Addr,
+        request_id: u64,
+        secret: &str,
+    ) -> Result<AppResponse, MarketplaceError> {
+        let msg = suite
+            .marketplace
+            .confirm_delivery(request_id, secret)
+            .unwrap();
+        suite.execute(sender, msg)
+    }
+
+    #[test]
+    fn seller_confirms_delivery_with_buyer_secret() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let marketplace = suite.marketplace.addr();
+
+        let request_id = suite.accepted_request(500);
+        let code = delivery_code(request_id, "correct horse battery staple");
+        let msg = suite
+            .marketplace
+            .pay_for_request(
+                request_id,
+                CoinPayment::Cosmos,
+                None,
+                Some(code.clone()),
+                coins(500, COIN_DENOM),
+            )
+            .unwrap();
+        suite.execute(&buyer, msg).unwrap();
+        let history = suite
+            .marketplace
+            .user_payment_history(&suite.app.wrap(), buyer.to_string())
+            .unwrap();
+        assert_eq!(history.payments[0].delivery_code, Some(code));
+
+        let err = confirm(
+            &mut suite,
+            &buyer,
+            request_id,
+            "correct horse battery staple",
+        )
+        .unwrap_err();
+        assert!(matches!(err, MarketplaceError::UnauthorizedSeller));
+        let err = confirm(&mut suite, &seller, request_id, "wrong").unwrap_err();
+        assert!(matches!(err, MarketplaceError::DeliveryCodeMismatch));
+
+        // No need to wait for the lock window or the buyer.
+        let res = confirm(
+            &mut suite,
+            &seller,
+            request_id,
+            "correct horse battery staple",
+        )
+        .unwrap();
+        assert_eq!(
+            event_id(&res, "marketplace/request_completed", "request_id"),
+            request_id
+        );
+        assert_eq!(suite.lifecycle(request_id), RequestLifecycle::Completed);
+        assert_eq!(suite.native_balance(&marketplace), 0);
+        assert_eq!(suite.native_balance(&seller), INITIAL_BALANCE + 500);
+
+        let err = confirm(
+            &mut suite,
+            &seller,
+            request_id,
+            "correct horse battery staple",
+        )
+        .unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestNotPaid));
+    }
+
+    #[test]
+    fn delivery_code_must_be_set_and_well_formed() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+
+        let request_id = suite.accepted_request(500);
+        let msg = suite
+            .marketplace
+            .pay_for_request(
+                request_id,
+                CoinPayment::Cosmos,
+                None,
+                Some(HexBinary::from(b"secret")),
+                coins(500, COIN_DENOM),
+            )
+            .unwrap();
+        let err = suite.execute(&buyer, msg).unwrap_err();
+        assert!(matches!(err, MarketplaceError::InvalidCommitment));
+
+        suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(500, COIN_DENOM),
+            )
+            .unwrap();
+        let err = confirm(&mut suite, &seller, request_id, "secret").unwrap_err();
+        assert!(matches!(err, MarketplaceError::DeliveryCodeNotSet));
+    }
+}
+
+mod moderation {
+    use super::*;
+
+    fn moderator(suite: &mut Suite) -> Addr {
+        let moderator = suite.app.api().addr_make("moderator");
+        let msg = suite.marketplace.add_moderator(&moderator).unwrap();
+        let admin = suite.admin.clone();
+        suite.execute(&admin, msg).unwrap();
+        moderator
+    }
+
+    fn suspend(suite: &mut Suite, sender: &Addr, user: &Addr, suspended: bool) {
+        let msg = if suspended {
+            suite.marketplace.suspend_user(user).unwrap()
+        } else {
+            suite.marketplace.unsuspend_user(user).unwrap()
+        };
+        suite.execute(sender, msg).unwrap();
+    }
+
+    #[test]
+    fn moderators_are_managed_by_admin() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let admin = suite.admin.clone();
+
+        let msg = suite.marketplace.add_moderator(&buyer).unwrap();
+        let err = suite.execute(&buyer, msg).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Unauthorized));
+
+        let msg = suite.marketplace.suspend_user(&seller).unwrap();
+        let err = suite.execute(&buyer, msg).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Unauthorized));
+
+        let moderator = moderator(&mut suite);
+        let moderators = suite
+            .marketplace
+            .moderators(&suite.app.wrap())
+            .unwrap()
+            .moderators;
+        assert_eq!(moderators, vec![moderator.clone()]);
+
+        // The admin moderates without being listed.
+        suspend(&mut suite, &admin, &seller, true);
+        suspend(&mut suite, &moderator, &seller, false);
+
+        let msg = suite.marketplace.remove_moderator(&moderator).unwrap();
+        suite.execute(&admin, msg).unwrap();
+        let msg = suite.marketplace.suspend_user(&seller).unwrap();
+        let err = suite.execute(&moderator, msg).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Unauthorized));
+        assert!(suite
+            .marketplace
+            .moderators(&suite.app.wrap())
+            .unwrap()
+            .moderators
+            .is_empty());
+    }
+
+    #[test]
+    fn suspended_users_are_rejected() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let moderator = moderator(&mut suite);
+
+        let request_id = suite.create_request();
+        let offer_id = suite.create_offer(request_id, 500);
+        let other_request = suite.create_request();
+
+        suspend(&mut suite, &moderator, &buyer, true);
+        suspend(&mut suite, &moderator, &seller, true);
+        let user = suite.marketplace.user(&suite.app.wrap(), &buyer).unwrap();
+        assert!(user.suspended);
+
+        let err = suite.try_create_request(&buyer, None).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Suspended));
+        let err = suite.accept(&buyer, offer_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Suspended));
+        let err = suite
+            .try_create_offer(&seller, other_request, 500, None)
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::Suspended));
+        let msg = suite
+            .marketplace
+            .update_offer(offer_id, 400, vec![], "Store", None, None, vec![])
+            .unwrap();
+        let err = suite.execute(&seller, msg).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Suspended));
+        let msg = suite.marketplace.counter_offer(offer_id, 400).unwrap();
+        let err = suite.execute(&buyer, msg.clone()).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Suspended));
+
+        // The buyer may negotiate again, the suspended seller may not answer.
+        suspend(&mut suite, &moderator, &buyer, false);
+        suite.execute(&buyer, msg).unwrap();
+        let msg = suite
+            .marketplace
+            .respond_to_counter_offer(offer_id, true)
+            .unwrap();
+        let err = suite.execute(&seller, msg).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Suspended));
+        let msg = suite.marketplace.counter_offer(offer_id, 450).unwrap();
+        let err = suite.execute(&seller, msg).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Suspended));
+        let err = suite.accept(&buyer, offer_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Suspended));
+
+        suspend(&mut suite, &moderator, &seller, false);
+        let user = suite.marketplace.user(&suite.app.wrap(), &buyer).unwrap();
+        assert!(!user.suspended);
+        suite.accept(&buyer, offer_id).unwrap();
+        suite
+            .try_create_offer(&seller, other_request, 500, None)
+            .unwrap();
+    }
+
+    #[test]
+    fn hidden_listings_are_dropped_from_list_queries() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+        let other = suite.user("other-seller", AccountType::Seller);
+        let moderator = moderator(&mut suite);
+
+        let request_id = suite.create_request();
+        let scam = suite.create_offer(request_id, 1);
+        let offer_id = suite
+            .try_create_offer(&other, request_id, 500, None)
+            .unwrap();
+        let visible = suite.create_request();
+
+        let msg = suite.marketplace.set_request_hidden(visible, true).unwrap();
+        suite.execute(&moderator, msg).unwrap();
+        let msg = suite.marketplace.set_offer_hidden(scam, true).unwrap();
+        suite.execute(&moderator, msg).unwrap();
+
+        let wrap = suite.app.wrap();
+        let requests = suite.marketplace.all_requests(&wrap).unwrap().requests;
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].id, request_id);
+        let requests = suite.marketplace.user_requests(&wrap, &buyer).unwrap();
+        assert_eq!(requests.requests.len(), 1);
+        // Still reachable directly, for audits.
+        let request = suite.marketplace.request(&wrap, visible).unwrap().request;
+        assert!(request.hidden);
+
+        let offers = suite
+            .marketplace
+            .offers_by_request(&wrap, request_id)
+            .unwrap()
+            .offers;
+        assert_eq!(offers.len(), 1);
+        assert_eq!(offers[0].id, offer_id);
+        let offers = suite.marketplace.seller_offers(&wrap, &seller).unwrap();
+        assert!(offers.offers.is_empty());
+
+        let err = suite.accept(&buyer, scam).unwrap_err();
+        assert!(matches!(err, MarketplaceError::OfferNotFound));
+
+        let msg = suite.marketplace.set_offer_hidden(scam, false).unwrap();
+        suite.execute(&moderator, msg).unwrap();
+        let offers = suite
+            .marketplace
+            .offers_by_request(&suite.app.wrap(), request_id)
+            .unwrap();
+        assert_eq!(offers.offers.len(), 2);
+        suite.accept(&buyer, scam).unwrap();
+    }
+}
+
+mod pause {
+    use super::*;
+    use crate::helpers::bid_commitment;
+    use crate::state::{Installment, PauseFlags, PaymentSchedule, SealedAuction, UNBONDING_PERIOD};
+
+    fn set_pause(
+        suite: &mut Suite,
+        all: Option<bool>,
+        payments: Option<bool>,
+        listings: Option<bool>,
+    ) {
+        let msg = suite
+            .marketplace
+            .set_pause(all, payments, listings)
+            .unwrap();
+        let admin = suite.admin.clone();
+        suite.execute(&admin, msg).unwrap();
+    }
+
+    #[test]
+    fn pause_is_admin_only() {
+        let mut suite = Suite::new();
+        let buyer = suite.buyer.clone();
+
+        let msg = suite.marketplace.set_pause(Some(true), None, None).unwrap();
+        let err = suite.execute(&buyer, msg).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Unauthorized));
+
+        set_pause(&mut suite, None, Some(true), None);
+        set_pause(&mut suite, None, None, Some(true));
+        let status = suite.marketplace.pause_status(&suite.app.wrap()).unwrap();
+        assert_eq!(
+            status.paused,
+            PauseFlags {
+                all: false,
+                payments: true,
+                listings: true,
+            }
+        );
+    }
+
+    #[test]
+    fn paused_payments_block_escrow_but_not_refunds() {
+        let mut suite = Suite::new();
+        let buyer = suite.buyer.clone();
+
+        let request_id = suite.accepted_request(500);
+        let open_request = suite.create_request();
+        set_pause(&mut suite, None, Some(true), None);
+
+        let err = suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(500, COIN_DENOM),
+            )
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::Paused));
+        // Listings are unaffected and buyers can still back out.
+        suite.create_request();
+        let msg = suite.marketplace.cancel_request(open_request).unwrap();
+        suite.execute(&buyer, msg).unwrap();
+
+        set_pause(&mut suite, None, Some(false), None);
+        suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(500, COIN_DENOM),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn paused_listings_block_new_requests_and_offers() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+
+        let request_id = suite.create_request();
+        set_pause(&mut suite, None, None, Some(true));
+
+        let err = suite.try_create_request(&buyer, None).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Paused));
+        let err = suite
+            .try_create_offer(&seller, request_id, 500, None)
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::Paused));
+
+        set_pause(&mut suite, None, None, Some(false));
+        suite.create_offer(request_id, 500);
+    }
+
+    #[test]
+    fn paused_listings_block_offer_changes() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+
+        let request_id = suite.create_request();
+        let offer_id = suite.create_offer(request_id, 500);
+        let now = suite.now();
+        let auction = SealedAuction {
+            bidding_ends_at: now + 100,
+            reveal_ends_at: now + 200,
+            auto_select: false,
+        };
+        let msg = suite
+            .marketplace
+            .create_request(
+                "Rice",
+                "50kg bag",
+                vec![],
+                6_524_379,
+                3_379_206,
+                None,
+                None,
+                vec![],
+                None,
+                Some(auction),
+                None,
+            )
+            .unwrap();
+        let res = suite.execute(&buyer, msg).unwrap();
+        let auction_id = event_id(&res, "marketplace/request_created", "request_id");
+        let commitment = bid_commitment(auction_id, seller.as_str(), 400, "salt");
+        let msg = suite
+            .marketplace
+            .commit_offer(auction_id, commitment, vec![], "Store")
+            .unwrap();
+        let res = suite.execute(&seller, msg).unwrap();
+        let sealed_id = event_id(&res, "marketplace/offer_committed", "offer_id");
+        suite.advance(100);
+        set_pause(&mut suite, None, None, Some(true));
+
+        let msg = suite
+            .marketplace
+            .update_offer(offer_id, 450, vec![], "Store", None, None, vec![])
+            .unwrap();
+        let err = suite.execute(&seller, msg).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Paused));
+        let msg = suite.marketplace.counter_offer(offer_id, 400).unwrap();
+        let err = suite.execute(&buyer, msg).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Paused));
+        let msg = suite
+            .marketplace
+            .respond_to_counter_offer(offer_id, true)
+            .unwrap();
+        let err = suite.execute(&seller, msg).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Paused));
+        let msg = suite
+            .marketplace
+            .reveal_offer(sealed_id, 400, "salt")
+            .unwrap();
+        let err = suite.execute(&seller, msg.clone()).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Paused));
+
+        set_pause(&mut suite, None, None, Some(false));
+        suite.execute(&seller, msg).unwrap();
+    }
+
+    #[test]
+    fn deposits_cannot_be_forfeited_while_payments_are_paused() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+
+        let request_id = suite.create_request();
+        let schedule = PaymentSchedule {
+            deposit: 200,
+            installments: vec![
+                Installment {
+                    amount: 300,
+                    due_after: 1_000,
+                },
+                Installment {
+                    amount: 500,
+                    due_after: 2_000,
+                },
+            ],
+        };
+        let msg = suite
+            .marketplace
+            .create_offer(
+                request_id,
+                1_000,
+                vec![],
+                "Store",
+                None,
+                Some(schedule),
+                vec![],
+            )
+            .unwrap();
+        let res = suite.execute(&seller, msg).unwrap();
+        let offer_id = event_id(&res, "marketplace/offer_created", "offer_id");
+        suite.accept(&buyer, offer_id).unwrap();
+        suite.advance(TIME_TO_LOCK);
+        suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(200, COIN_DENOM),
+            )
+            .unwrap();
+
+        // Paused before the installment is due: the buyer cannot pay it, so
+        // the seller must not be able to claim the deposit either.
+        set_pause(&mut suite, None, Some(true), None);
+        suite.advance(1_001);
+        let err = suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(300, COIN_DENOM),
+            )
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::Paused));
+        let msg = suite.marketplace.forfeit_deposit(request_id).unwrap();
+        let err = suite.execute(&seller, msg.clone()).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Paused));
+
+        set_pause(&mut suite, Some(true), Some(false), None);
+        let err = suite.execute(&seller, msg.clone()).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Paused));
+
+        set_pause(&mut suite, Some(false), None, None);
+        suite.execute(&seller, msg).unwrap();
+        assert_eq!(suite.native_balance(&buyer), INITIAL_BALANCE - 200);
+        assert_eq!(suite.native_balance(&seller), INITIAL_BALANCE + 200);
+    }
+
+    #[test]
+    fn pausing_all_leaves_withdrawals_open() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+
+        let msg = suite
+            .marketplace
+            .bond_stake(coins(1_000, COIN_DENOM))
+            .unwrap();
+        suite.execute(&seller, msg).unwrap();
+        let request_id = suite.create_request();
+        let offer_id = suite.create_offer(request_id, 500);
+        set_pause(&mut suite, Some(true), None, None);
+
+        let err = suite.accept(&buyer, offer_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Paused));
+        let err = suite.register(&buyer, AccountType::Buyer).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Paused));
+
+        let msg = suite.marketplace.unbond_stake(Uint128::new(1_000)).unwrap();
+        suite.execute(&seller, msg).unwrap();
+        suite.advance(UNBONDING_PERIOD);
+        let msg = suite.marketplace.withdraw_stake().unwrap();
+        suite.execute(&seller, msg).unwrap();
+        assert_eq!(suite.native_balance(&seller), INITIAL_BALANCE);
+
+        let msg = suite.marketplace.cancel_request(request_id).unwrap();
+        suite.execute(&buyer, msg).unwrap();
+    }
+}
+
+mod errors {
+    use super::*;
+
+    #[test]
+    fn account_types_are_enforced() {
+        let mut suite = Suite::new();
+        let (buyer, seller) = (suite.buyer.clone(), suite.seller.clone());
+
+        let err = suite.create_store(&buyer).unwrap_err();
+        assert!(matches!(err, MarketplaceError::OnlySellersAllowed));
+
+        let err = suite.try_create_request(&seller, None).unwrap_err();
+        assert!(matches!(err, MarketplaceError::OnlyBuyersAllowed));
+
+        let request_id = suite.create_request();
+        let err = suite
+            .try_create_offer(&buyer, request_id, 100, None)
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::OnlySellersAllowed));
+
+        let offer_id = suite.create_offer(request_id, 100);
+        let err = suite.accept(&seller, offer_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::OnlyBuyersAllowed));
+    }
+
+    #[test]
+    fn only_the_request_owner_can_act() {
+        let mut suite = Suite::new();
+        let other_buyer = suite.user("other_buyer", AccountType::Buyer);
+        let request_id = suite.create_request();
+        let offer_id = suite.create_offer(request_id, 100);
+
+        let err = suite.accept(&other_buyer, offer_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::UnauthorizedBuyer));
+
+        let msg = suite.marketplace.delete_request(request_id).unwrap();
+        let err = suite.execute(&other_buyer, msg).unwrap_err();
+        assert!(matches!(err, MarketplaceError::UnauthorizedBuyer));
+
+        let buyer = suite.buyer.clone();
+        suite.accept(&buyer, offer_id).unwrap();
+        suite.advance(TIME_TO_LOCK);
+        let err = suite
+            .pay(&other_buyer, request_id, CoinPayment::Cosmos, vec![])
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::UnauthorizedBuyer));
+    }
+
+    #[test]
+    fn payment_errors() {
+        let mut suite = Suite::new();
+        let buyer = suite.buyer.clone();
+
+        let unaccepted = suite.create_request();
+        let err = suite
+            .pay(
+                &buyer,
+                unaccepted,
+                CoinPayment::Cosmos,
+                coins(100, COIN_DENOM),
+            )
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestNotAccepted));
+
+        let request_id = suite.accepted_request(100);
+        let err = suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(99, COIN_DENOM),
+            )
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::InsufficientFunds));
+
+        suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(100, COIN_DENOM),
+            )
+            .unwrap();
+        let err = suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(100, COIN_DENOM),
+            )
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestAlreadyPaid));
+    }
+
+    #[test]
+    fn stray_denoms_are_rejected() {
+        let mut suite = Suite::new();
+        let buyer = suite.buyer.clone();
+        let marketplace = suite.marketplace.addr();
+        let request_id = suite.accepted_request(100);
+
+        let err = suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                vec![coin(100, COIN_DENOM), coin(1, OTHER_DENOM)],
+            )
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::UnexpectedFunds { denom } if denom == OTHER_DENOM));
+
+        suite.approve_usdt(&buyer, 100);
+        let err = suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::USDT,
+                coins(100, COIN_DENOM),
+            )
+            .unwrap_err();
+        assert!(matches!(err, MarketplaceError::UnexpectedFunds { denom } if denom == COIN_DENOM));
+
+        for denom in [COIN_DENOM, OTHER_DENOM] {
+            let balance = suite.app.wrap().query_balance(&marketplace, denom).unwrap();
+            assert!(balance.amount.is_zero());
+        }
+        assert_eq!(suite.native_balance(&buyer), INITIAL_BALANCE);
+    }
+
+    #[test]
+    fn completion_errors() {
+        let mut suite = Suite::new();
+        let buyer = suite.buyer.clone();
+
+        let request_id = suite.accepted_request(100);
+        let err = suite.complete(&buyer, request_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestNotAccepted));
+
+        suite
+            .pay(
+                &buyer,
+                request_id,
+                CoinPayment::Cosmos,
+                coins(100, COIN_DENOM),
+            )
+            .unwrap();
+        let err = suite.complete(&buyer, request_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestNotLocked));
+
+        suite.advance(TIME_TO_LOCK);
+        suite.complete(&buyer, request_id).unwrap();
+        let err = suite.complete(&buyer, request_id).unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestNotAccepted));
+    }
+
+    #[test]
+    fn locked_request_cannot_change_hands() {
+        let mut suite = Suite::new();
+        let buyer = suite.buyer.clone();
+        let other = suite.user("other_seller", AccountType::Seller);
+        let request_id = suite.create_request();
+        let first = suite.create_offer(request_id, 100);
+        let second = suite
+            .try_create_offer(&other, request_id, 90, None)
+            .unwrap();
+
+        suite.accept(&buyer, first).unwrap();
+        let err = suite.accept(&buyer, first).unwrap_err();
+        assert!(matches!(err, MarketplaceError::OfferAlreadyAccepted));
+
+        suite.advance(TIME_TO_LOCK + 1);
+        let err = suite.accept(&buyer, second).unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestLocked));
+
+        let msg = suite.marketplace.delete_request(request_id).unwrap();
+        let err = suite.execute(&buyer, msg).unwrap_err();
+        assert!(matches!(err, MarketplaceError::RequestLocked));
+    }
+
+    #[test]
+    fn config_is_admin_only() {
+        let mut suite = Suite::new();
+        let (admin, buyer) = (suite.admin.clone(), suite.buyer.clone());
+
+        let msg = suite
+            .marketplace
+            .update_config(
+                Some(60),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        let err = suite.execute(&buyer, msg.clone()).unwrap_err();
+        assert!(matches!(err, MarketplaceError::Unauthorized));
+
+        suite.execute(&admin, msg).unwrap();
+        let config = suite.marketplace.config(&suite.app.wrap()).unwrap().config;
+        assert_eq!(config.time_to_lock, 60);
+        assert_eq!(config.usdt_addr, suite.usdt);
+    }
+}
+
+/// Drives the marketplace with random operation sequences and checks after
+/// every step that the contract holds exactly the open escrow, the creation
+/// fees of open requests and the sellers' stake.
+mod invariants {
+    use super::*;
+    use crate::msg::ExecuteMsg;
+    use crate::state::{DeliveryStage, Installment, PaymentSchedule, Request};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::HashMap;
+
+    const SEEDS: u64 = 16;
+    const STEPS: usize = 300;
+    const MIN_STAKE: u128 = 100;
+    const CREATION_FEE: u128 = 10;
+
+    struct Model {
+        buyers: Vec<Addr>,
+        sellers: Vec<Addr>,
+        request_ids: Vec<u64>,
+        offer_ids: Vec<u64>,
+        request_owners: HashMap<u64, Addr>,
+        offer_requests: HashMap<u64, u64>,
+        /// Everything paid into escrow per request, across installments.
+        escrowed: HashMap<u64, u128>,
+        total_native: u128,
+        total_usdt: u128,
+    }
+
+    impl Model {
+        fn new(suite: &mut Suite) -> Self {
+            let admin = suite.admin.clone();
+            let buyer = suite.buyer.clone();
+            let seller = suite.seller.clone();
+            let other_buyer = suite.user("other_buyer", AccountType::Buyer);
+            let other_seller = suite.user("other_seller", AccountType::Seller);
+
+            // Split the initial balances so both buyers can pay in either coin
+            // and both sellers can bond stake.
+            suite
+                .app
+                .send_tokens(
+                    buyer.clone(),
+                    other_buyer.clone(),
+                    &coins(INITIAL_BALANCE / 2, COIN_DENOM),
+                )
+                .unwrap();
+            suite
+                .app
+                .send_tokens(
+                    seller.clone(),
+                    other_seller.clone(),
+                    &coins(INITIAL_BALANCE / 2, COIN_DENOM),
+                )
+                .unwrap();
+            let transfer = cw20::Cw20ExecuteMsg::Transfer {
+                recipient: other_buyer.to_string(),
+                amount: Uint128::new(INITIAL_BALANCE / 2),
+            };
+            suite
+                .app
+                .execute_contract(buyer.clone(), suite.usdt.clone(), &transfer, &[])
+                .unwrap();
+
+            let msg = suite
+                .marketplace
+                .update_config(
+                    None,
+                    None,
+                    None,
+                    None,
+                    Some(Uint128::new(MIN_STAKE)),
+                    Some(TIME_TO_LOCK),
+                    None,
+                    None,
+                    None,
+                    Some(Uint128::new(CREATION_FEE)),
+                    Some(TIME_TO_LOCK),
+                )
+                .unwrap();
+            suite.execute(&admin, msg).unwrap();
+
+            let sellers = vec![seller, other_seller];
+            for seller in &sellers {
+                let msg = suite
+                    .marketplace
+                    .bond_stake(coins(2 * MIN_STAKE, COIN_DENOM))
+                    .unwrap();
+                suite.execute(seller, msg).unwrap();
+            }
+
+            let model = Model {
+                buyers: vec![buyer, other_buyer],
+                sellers,
+                request_ids: vec![],
+                offer_ids: vec![],
+                request_owners: HashMap::new(),
+                offer_requests: HashMap::new(),
+                escrowed: HashMap::new(),
+                total_native: 0,
+                total_usdt: 0,
+            };
+            Model {
+                total_native: model.native_total(suite),
+                total_usdt: model.usdt_total(suite),
+                ..model
+            }
+        }
+
+        fn accounts(&self, suite: &Suite) -> Vec<Addr> {
+            let mut accounts = self.buyers.clone();
+            accounts.extend(self.sellers.iter().cloned());
+            accounts.push(suite.admin.clone());
+            accounts.push(suite.marketplace.addr());
+            accounts
+        }
+
+        fn native_total(&self, suite: &Suite) -> u128 {
+            self.accounts(suite)
+                .iter()
+                .map(|addr| suite.native_balance(addr))
+                .sum()
+        }
+
+        fn usdt_total(&self, suite: &Suite) -> u128 {
+            self.accounts(suite)
+                .iter()
+                .map(|addr| suite.usdt_balance(addr))
+                .sum()
+        }
+
+        /// Every request still in storage, including cancelled ones that list
+        /// queries leave out.
+        fn requests(&self, suite: &Suite) -> Vec<Request> {
+            self.request_ids
+                .iter()
+                .filter_map(|id| suite.marketplace.request(&suite.app.wrap(), *id).ok())
+                .map(|res| res.request)
+                .collect()
+        }
+
+        /// Mostly the request's owner, sometimes a random buyer to hit the
+        /// authorization checks.
+        fn actor(&self, rng: &mut StdRng, request_id: u64) -> Addr {
+            match self.request_owners.get(&request_id) {
+                Some(owner) if rng.gen_bool(0.8) => owner.clone(),
+                _ => self.buyers[rng.gen_range(0..self.buyers.len())].clone(),
+            }
+        }
+
+        fn step(&mut self, suite: &mut Suite, rng: &mut StdRng) {
+            let seller = self.sellers[rng.gen_range(0..self.sellers.len())].clone();
+            let offer_id = pick(rng, &self.offer_ids);
+            let offer_request_id = self.offer_requests.get(&offer_id).copied();
+            let request_id = match offer_request_id {
+                Some(request_id) if rng.gen_bool(0.5) => request_id,
+                _ => pick(rng, &self.request_ids),
+            };
+
+            let _ = match rng.gen_range(0..30) {
+                0..=1 => {
+                    let buyer = self.buyers[rng.gen_range(0..self.buyers.len())].clone();
+                    self.create_request(suite, rng, &buyer)
+                }
+                2..=4 => self.create_offer(suite, rng, &seller, request_id),
+                5..=7 => {
+                    let buyer = self.actor(rng, offer_request_id.unwrap_or_default());
+                    suite.accept(&buyer, offer_id).map(|_| ())
+                }
+                8..=12 => {
+                    let buyer = self.actor(rng, request_id);
+                    self.pay(suite, rng, &buyer, request_id)
+                }
+                13..=14 => {
+                    let buyer = self.actor(rng, request_id);
+                    suite.complete(&buyer, request_id).map(|_| ())
+                }
+                15 => {
+                    let buyer = self.actor(rng, request_id);
+                    let msg = if rng.gen_bool(0.5) {
+                        suite.marketplace.delete_request(request_id)
+                    } else {
+                        suite.marketplace.cancel_request(request_id)
+                    };
+                    suite.execute(&buyer, msg.unwrap()).map(|_| ())
+                }
+                16..=17 => {
+                    let msg = suite.marketplace.forfeit_deposit(request_id).unwrap();
+                    suite.execute(&seller, msg).map(|_| ())
+                }
+                18 => {
+                    let admin = if rng.gen_bool(0.8) {
+                        suite.admin.clone()
+                    } else {
+                        self.actor(rng, request_id)
+                    };
+                    let msg = suite.marketplace.slash_stake(request_id).unwrap();
+                    suite.execute(&admin, msg).map(|_| ())
+                }
+                19..=20 => {
+                    let msg = suite
+                        .marketplace
+                        .post_delivery_milestone(request_id, DeliveryStage::Delivered, None)
+                        .unwrap();
+                    suite.execute(&seller, msg).map(|_| ())
+                }
+                21 => {
+                    let buyer = self.actor(rng, request_id);
+                    let msg = suite.marketplace.release_escrow(request_id).unwrap();
+                    suite.execute(&buyer, msg).map(|_| ())
+                }
+                22 => {
+                    let amount = rng.gen_range(1..MIN_STAKE);
+                    let msg = suite
+                        .marketplace
+                        .bond_stake(coins(amount, COIN_DENOM))
+                        .unwrap();
+                    suite.execute(&seller, msg).map(|_| ())
+                }
+                23 => {
+                    let amount = Uint128::new(rng.gen_range(1..MIN_STAKE));
+                    let msg = suite.marketplace.unbond_stake(amount).unwrap();
+                    suite.execute(&seller, msg).map(|_| ())
+                }
+                24 => {
+                    let msg = suite.marketplace.withdraw_stake().unwrap();
+                    suite.execute(&seller, msg).map(|_| ())
+                }
+                _ => {
+                    suite.advance(rng.gen_range(0..2 * TIME_TO_LOCK));
+                    Ok(())
+                }
+            };
+        }
+
+        /// Usually attaches the creation fee, sometimes a wrong one that must be
+        /// rejected without moving funds.
+        fn create_request(
+            &mut self,
+            suite: &mut Suite,
+            rng: &mut StdRng,
+            buyer: &Addr,
+        ) -> Result<(), MarketplaceError> {
+            let fee = match rng.gen_range(0..10) {
+                0 => CREATION_FEE + 1,
+                _ => CREATION_FEE,
+            };
+            let msg = ExecuteMsg::CreateRequest {
+                name: "Rice".to_string(),
+                description: "50kg bag".to_string(),
+                images: vec![],
+                latitude: 6_524_379,
+                longitude: 3_379_206,
+                expires_at: None,
+                time_to_lock: None,
+                line_items: vec![],
+                budget: None,
+                auction: None,
+                auto_accept: None,
+            };
+            let msg = suite
+                .marketplace
+                .call_with_funds(msg, coins(fee, COIN_DENOM))
+                .unwrap();
+            let res = suite.execute(buyer, msg)?;
+            let id = event_id(&res, "marketplace/request_created", "request_id");
+            self.request_ids.push(id);
+            self.request_owners.insert(id, buyer.clone());
+            Ok(())
+        }
+
+        /// Half of the offers are paid in installments: a deposit and up to
+        /// three installments a lock window apart.
+        fn create_offer(
+            &mut self,
+            suite: &mut Suite,
+            rng: &mut StdRng,
+            seller: &Addr,
+            request_id: u64,
+        ) -> Result<(), MarketplaceError> {
+            let price = rng.gen_range(4..1_000);
+            let payment_schedule = rng.gen_bool(0.5).then(|| {
+                let parts = rng.gen_range(1..=3);
+                let amount = price / (parts + 1);
+                PaymentSchedule {
+                    deposit: price - amount * parts,
+                    installments: (1..=parts)
+                        .map(|part| Installment {
+                            amount,
+                            due_after: part as u64 * TIME_TO_LOCK,
+                        })
+                        .collect(),
+                }
+            });
+            let msg = suite
+                .marketplace
+                .create_offer(
+                    request_id,
+                    price,
+                    vec![],
+                    "Store",
+                    None,
+                    payment_schedule,
+                    vec![],
+                )
+                .unwrap();
+            let res = suite.execute(seller, msg)?;
+            let id = event_id(&res, "marketplace/offer_created", "offer_id");
+            self.offer_ids.push(id);
+            self.offer_requests.insert(id, request_id);
+            Ok(())
+        }
+
+        fn pay(
+            &mut self,
+            suite: &mut Suite,
+            rng: &mut StdRng,
+            buyer: &Addr,
+            request_id: u64,
+        ) -> Result<(), MarketplaceError> {
+            let coin = if rng.gen_bool(0.5) {
+                CoinPayment::Cosmos
+            } else {
+                CoinPayment::USDT
+            };
+            let due =
+                match suite
+                    .marketplace
+                    .payment_quote(&suite.app.wrap(), request_id, coin.clone())
+                {
+                    Ok(quote) => quote.amount.u128(),
+                    Err(_) => return Ok(()),
+                };
+            let res = match coin {
+                CoinPayment::Cosmos => {
+                    // Occasionally under- or overpay; the excess must come straight back.
+                    let amount = match rng.gen_range(0..5) {
+                        0 => due.saturating_sub(1),
+                        1 => due + rng.gen_range(1..100),
+                        _ => due,
+                    };
+                    suite.pay(buyer, request_id, coin, coins(amount.max(1), COIN_DENOM))
+                }
+                CoinPayment::USDT => {
+                    suite.approve_usdt(buyer, due);
+                    suite.pay(buyer, request_id, coin, vec![])
+                }
+            };
+            res.map(|_| *self.escrowed.entry(request_id).or_default() += due)
+        }
+
+        fn check(&self, suite: &Suite) {
+            let marketplace = suite.marketplace.addr();
+            let mut owed_native = 0;
+            let mut owed_usdt = 0;
+            let mut fees = 0;
+
+            for request in self.requests(suite) {
+                if !self.escrowed.contains_key(&request.id) {
+                    assert!(!request.paid, "request {} paid", request.id);
+                    assert!(
+                        !matches!(
+                            request.lifecycle,
+                            RequestLifecycle::PartiallyPaid
+                                | RequestLifecycle::Paid
+                                | RequestLifecycle::Completed
+                                | RequestLifecycle::Defaulted
+                        ),
+                        "request {} is {:?} without a payment",
+                        request.id,
+                        request.lifecycle
+                    );
+                }
+                if !matches!(
+                    request.lifecycle,
+                    RequestLifecycle::Completed
+                        | RequestLifecycle::Cancelled
+                        | RequestLifecycle::Defaulted
+                ) {
+                    fees += request.creation_fee.u128();
+                }
+            }
+
+            for buyer in &self.buyers {
+                let history = suite
+                    .marketplace
+                    .user_payment_history(&suite.app.wrap(), buyer.to_string())
+                    .unwrap();
+                for payment in history.payments {
+                    assert_eq!(
+                        Some(&payment.amount.u128()),
+                        self.escrowed.get(&payment.request_id),
+                        "request {}",
+                        payment.request_id
+                    );
+                    let request = suite
+                        .marketplace
+                        .request(&suite.app.wrap(), payment.request_id)
+                        .unwrap()
+                        .request;
+                    match request.lifecycle {
+                        RequestLifecycle::PartiallyPaid | RequestLifecycle::Paid => {}
+                        RequestLifecycle::Completed | RequestLifecycle::Defaulted => continue,
+                        lifecycle => panic!("request {} paid while {:?}", request.id, lifecycle),
+                    }
+                    match payment.coin {
+                        CoinPayment::Cosmos => owed_native += payment.amount.u128(),
+                        CoinPayment::USDT => owed_usdt += payment.amount.u128(),
+                    }
+                }
+            }
+
+            let staked: u128 = self
+                .sellers
+                .iter()
+                .map(|seller| {
+                    let stake = suite
+                        .marketplace
+                        .seller_stake(&suite.app.wrap(), seller.to_string())
+                        .unwrap()
+                        .stake;
+                    (stake.bonded + stake.unbonding).u128()
+                })
+                .sum();
+
+            assert_eq!(
+                suite.native_balance(&marketplace),
+                owed_native + fees + staked
+            );
+            assert_eq!(suite.usdt_balance(&marketplace), owed_usdt);
+            assert_eq!(self.native_total(suite), self.total_native);
+            assert_eq!(self.usdt_total(suite), self.total_usdt);
+        }
+    }
+
+    fn pick(rng: &mut StdRng, ids: &[u64]) -> u64 {
+        if ids.is_empty() {
+            return 0;
+        }
+        ids[rng.gen_range(0..ids.len())]
+    }
+
+    #[test]
+    fn balance_matches_escrow_fees_and_stake() {
+        let mut lifecycles = vec![];
+        for seed in 0..SEEDS {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut suite = Suite::new();
+            let mut model = Model::new(&mut suite);
+
+            for _ in 0..STEPS {
+                model.step(&mut suite, &mut rng);
+                model.check(&suite);
+            }
+            lifecycles.extend(
+                model
+                    .requests(&suite)
+                    .into_iter()
+                    .map(|request| request.lifecycle),
+            );
+        }
+
+        // Every way out of escrow must have been exercised.
+        for lifecycle in [
+            RequestLifecycle::PartiallyPaid,
+            RequestLifecycle::Paid,
+            RequestLifecycle::Completed,
+            RequestLifecycle::Cancelled,
+            RequestLifecycle::Defaulted,
+        ] {
+            assert!(
+                lifecycles.contains(&lifecycle),
+                "never reached {:?}",
+                lifecycle
+            );
+        }
+    }
+}
@@ -1,8 +1,12 @@
 pub mod contract;
 mod error;
+pub mod events;
 pub mod helpers;
-pub mod integration_tests;
+#[cfg(test)]
+mod integration_tests;
 pub mod msg;
+pub mod oracle;
 pub mod state;
+pub mod validation;
 
 pub use crate::error::MarketplaceError;
@@ -1,13 +1,36 @@
-use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Addr, HexBinary, Uint128};
 
-use crate::state::AccountType;
+use crate::state::{
+    AccountType, AutoAccept, Budget, CoinPayment, Config, DeliveryMilestone, DeliveryStage,
+    LineItem, LineQuote, Offer, PauseFlags, PaymentInfo, PaymentSchedule, Request, SealedAuction,
+    SellerStake, Store, User,
+};
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct InstantiateMsg {}
+#[cw_serde]
+pub struct InstantiateMsg {
+    pub time_to_lock: Option<u64>,
+    pub min_time_to_lock: Option<u64>,
+    pub max_time_to_lock: Option<u64>,
+    /// CW20 stablecoin accepted for `CoinPayment::USDT`, defaults to `state::USDT_ADDR`.
+    pub usdt_addr: Option<String>,
+    /// Price oracle (see `oracle::OracleQueryMsg`), prices are paid 1:1 without one.
+    pub oracle: Option<String>,
+    /// Stake sellers must bond before making offers, none by default.
+    pub min_stake: Option<Uint128>,
+    /// Defaults to `state::UNBONDING_PERIOD`.
+    pub unbonding_period: Option<u64>,
+    /// Listing limits, unlimited when unset or 0.
+    pub max_open_requests: Option<u32>,
+    pub max_offers_per_request: Option<u32>,
+    pub max_sellers_per_request: Option<u32>,
+    /// Fee in `state::COIN_DENOM` for creating a request, none by default.
+    pub creation_fee: Option<Uint128>,
+    /// Defaults to `state::AUTO_RELEASE_AFTER`.
+    pub auto_release_after: Option<u64>,
+}
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-#[serde(rename_all = "snake_case")]
+#[cw_serde]
 pub enum ExecuteMsg {
     CreateUser {
         username: String,
@@ -30,21 +53,77 @@ pub enum ExecuteMsg {
         latitude: i128,
         longitude: i128,
     },
+    /// Must attach exactly the configured creation fee, if any.
     CreateRequest {
         name: String,
         description: String,
         images: Vec<String>,
         latitude: i128,
         longitude: i128,
+        expires_at: Option<u64>,
+        time_to_lock: Option<u64>,
+        line_items: Vec<LineItem>,
+        budget: Option<Budget>,
+        auction: Option<SealedAuction>,
+        auto_accept: Option<AutoAccept>,
     },
     CreateOffer {
         price: u128,
         images: Vec<String>,
         request_id: u64,
         store_name: String,
+        expires_at: Option<u64>,
+        payment_schedule: Option<PaymentSchedule>,
+        /// Per-line quotes; when given, `price` must equal their total.
+        line_quotes: Vec<LineQuote>,
+    },
+    /// Replaces the terms of the sender's open offer. A pending counter-offer
+    /// is rejected.
+    UpdateOffer {
+        offer_id: u64,
+        price: u128,
+        images: Vec<String>,
+        store_name: String,
+        expires_at: Option<u64>,
+        payment_schedule: Option<PaymentSchedule>,
+        line_quotes: Vec<LineQuote>,
+    },
+    /// Sealed bid on an auction request, see `helpers::bid_commitment`.
+    CommitOffer {
+        request_id: u64,
+        commitment: HexBinary,
+        images: Vec<String>,
+        store_name: String,
+    },
+    RevealOffer {
+        offer_id: u64,
+        price: u128,
+        salt: String,
+    },
+    /// Accepts the lowest revealed bid of an `auto_select` auction; anyone may call it.
+    SettleAuction {
+        request_id: u64,
+    },
+    /// Accepts the best offer once the `auto_accept` deadline has passed; anyone may call it.
+    FinalizeRequest {
+        request_id: u64,
+    },
+    /// Proposes a new price for an offer. The buyer may counter at any time,
+    /// the seller only in reply to the buyer's pending counter-offer.
+    CounterOffer {
+        offer_id: u64,
+        price: u128,
+    },
+    /// Accepts or rejects the other party's pending counter-offer.
+    RespondToCounterOffer {
+        offer_id: u64,
+        accept: bool,
     },
+    /// Accepts the offer at its latest agreed price.
     AcceptOffer {
         offer_id: u64,
+        /// Accept only these quoted lines; the whole offer when omitted or empty.
+        lines: Option<Vec<u32>>,
     },
     ToggleLocation {
         enabled: bool,
@@ -52,31 +131,235 @@ pub enum ExecuteMsg {
     DeleteRequest {
         request_id: u64,
     },
+    CancelRequest {
+        request_id: u64,
+    },
     MarkRequestAsCompleted {
         request_id: u64,
     },
+    PayForRequest {
+        request_id: u64,
+        coin: CoinPayment,
+        /// Slippage bound: fail instead of paying more than this after conversion.
+        max_amount: Option<Uint128>,
+        /// Commitment to a secret the seller redeems with `ConfirmDelivery`,
+        /// see `helpers::delivery_code`. Replaces any code set by an earlier installment.
+        delivery_code: Option<HexBinary>,
+    },
+    ForfeitDeposit {
+        request_id: u64,
+    },
+    /// Seller reports fulfilment progress on a paid request.
+    PostDeliveryMilestone {
+        request_id: u64,
+        stage: DeliveryStage,
+        tracking_ref: Option<String>,
+    },
+    /// Seller completes a paid request with the secret the buyer handed over.
+    ConfirmDelivery {
+        request_id: u64,
+        secret: String,
+    },
+    /// Completes a delivered request the buyer has not confirmed once the
+    /// auto-release window has passed; anyone may call it.
+    ReleaseEscrow {
+        request_id: u64,
+    },
+    /// Bonds the attached `state::COIN_DENOM` as the sender's seller stake.
+    BondStake {},
+    /// Starts the unbonding period for `amount` of the bonded stake.
+    UnbondStake {
+        amount: Uint128,
+    },
+    /// Pays out stake whose unbonding period has passed.
+    WithdrawStake {},
+    /// Admin ruling against the seller of a paid request (lost dispute or
+    /// non-delivery): the escrow is refunded and `min_stake` is slashed to the buyer.
+    SlashStake {
+        request_id: u64,
+    },
+    /// Admin grants an address moderator rights.
+    AddModerator {
+        address: String,
+    },
+    RemoveModerator {
+        address: String,
+    },
+    /// Moderator bars a user from creating requests and offers and from
+    /// accepting offers. Existing escrows are not affected.
+    SuspendUser {
+        address: String,
+    },
+    UnsuspendUser {
+        address: String,
+    },
+    /// Moderator hides a request from (or restores it to) list queries.
+    SetRequestHidden {
+        request_id: u64,
+        hidden: bool,
+    },
+    /// Moderator hides an offer from list queries; hidden offers cannot be accepted.
+    SetOfferHidden {
+        offer_id: u64,
+        hidden: bool,
+    },
+    /// Admin sets the emergency pause flags; unset flags are left as they are.
+    SetPause {
+        all: Option<bool>,
+        payments: Option<bool>,
+        listings: Option<bool>,
+    },
+    UpdateConfig {
+        time_to_lock: Option<u64>,
+        min_time_to_lock: Option<u64>,
+        max_time_to_lock: Option<u64>,
+        oracle: Option<String>,
+        min_stake: Option<Uint128>,
+        unbonding_period: Option<u64>,
+        max_open_requests: Option<u32>,
+        max_offers_per_request: Option<u32>,
+        max_sellers_per_request: Option<u32>,
+        creation_fee: Option<Uint128>,
+        auto_release_after: Option<u64>,
+    },
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-#[serde(rename_all = "snake_case")]
+#[cw_serde]
+#[derive(QueryResponses)]
 pub enum QueryMsg {
+    #[returns(UserResponse)]
     GetUser { address: String },
 
+    #[returns(RequestResponse)]
     GetRequest { request_id: u64 },
 
+    #[returns(RequestsResponse)]
     GetAllRequests {},
 
+    /// Open requests whose budget is in `coin` (`PRICE_UNIT` when unset) and
+    /// between `min_price` and `max_price`, both inclusive.
+    #[returns(RequestsResponse)]
+    GetRequestsByBudget {
+        min_price: Option<u128>,
+        max_price: Option<u128>,
+        coin: Option<CoinPayment>,
+    },
+
+    #[returns(RequestsResponse)]
     GetUserRequests { address: String },
+    #[returns(PaymentHistoryResponse)]
     GetUserPaymentHistory { address: String },
 
+    #[returns(OfferResponse)]
     GetOffer { offer_id: u64 },
 
+    #[returns(OffersResponse)]
     GetOffersByRequest { request_id: u64 },
 
+    #[returns(UserResponse)]
     GetUserById { user_id: u64 },
 
+    #[returns(LocationPreferenceResponse)]
     GetLocationPreference { address: String },
+    #[returns(StoresResponse)]
     GetUserStores { address: String },
 
+    #[returns(OffersResponse)]
     GetSellerOffers { address: String },
+
+    #[returns(ConfigResponse)]
+    GetConfig {},
+
+    #[returns(PaymentQuoteResponse)]
+    GetPaymentQuote { request_id: u64, coin: CoinPayment },
+
+    #[returns(SellerStakeResponse)]
+    GetSellerStake { address: String },
+
+    #[returns(DeliveryStatusResponse)]
+    GetDeliveryStatus { request_id: u64 },
+
+    #[returns(ModeratorsResponse)]
+    GetModerators {},
+
+    #[returns(PauseStatusResponse)]
+    PauseStatus {},
+}
+
+#[cw_serde]
+pub struct UserResponse {
+    pub user: User,
+    pub completed_sales: u64,
+    pub suspended: bool,
+}
+
+#[cw_serde]
+pub struct RequestResponse {
+    pub request: Request,
+    pub locked_until: Option<u64>,
+}
+
+#[cw_serde]
+pub struct RequestsResponse {
+    pub requests: Vec<Request>,
+}
+
+#[cw_serde]
+pub struct OfferResponse {
+    pub offer: Offer,
+}
+
+#[cw_serde]
+pub struct OffersResponse {
+    pub offers: Vec<Offer>,
+}
+
+#[cw_serde]
+pub struct StoresResponse {
+    pub stores: Vec<Store>,
+}
+
+#[cw_serde]
+pub struct PaymentHistoryResponse {
+    pub payments: Vec<PaymentInfo>,
+}
+
+#[cw_serde]
+pub struct LocationPreferenceResponse {
+    pub enabled: bool,
+}
+
+#[cw_serde]
+pub struct ConfigResponse {
+    pub config: Config,
+}
+
+#[cw_serde]
+pub struct PaymentQuoteResponse {
+    /// Amount due now in `state::PRICE_UNIT`.
+    pub price: Uint128,
+    /// `price` converted into the requested coin at the current oracle price.
+    pub amount: Uint128,
+}
+
+#[cw_serde]
+pub struct SellerStakeResponse {
+    pub stake: SellerStake,
+}
+
+#[cw_serde]
+pub struct PauseStatusResponse {
+    pub paused: PauseFlags,
+}
+
+#[cw_serde]
+pub struct ModeratorsResponse {
+    pub moderators: Vec<Addr>,
+}
+
+#[cw_serde]
+pub struct DeliveryStatusResponse {
+    pub milestones: Vec<DeliveryMilestone>,
+    /// When `ReleaseEscrow` becomes possible, once delivered and still paid.
+    pub auto_release_at: Option<u64>,
 }
@@ -0,0 +1,59 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Decimal, QuerierWrapper, Uint128};
+
+use crate::error::MarketplaceError;
+use crate::state::{CoinPayment, Config, COIN_DENOM};
+
+// Offer prices are quoted in `state::PRICE_UNIT`. Any contract answering the
+// query below can be configured as the oracle that converts them into the
+// payment denom at the moment the buyer pays.
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum OracleQueryMsg {
+    /// Price of one base unit of `denom` (a native denom or a CW20 address),
+    /// expressed in base units of `state::PRICE_UNIT`.
+    #[returns(PriceResponse)]
+    Price { denom: String },
+}
+
+#[cw_serde]
+pub struct PriceResponse {
+    pub price: Decimal,
+}
+
+/// The denom the oracle is asked about for a payment option.
+pub fn denom(config: &Config, coin: &CoinPayment) -> String {
+    match coin {
+        CoinPayment::Cosmos => COIN_DENOM.to_string(),
+        CoinPayment::USDT => config.usdt_addr.to_string(),
+    }
+}
+
+/// Converts `price` (in price units) into the amount of `coin` to pay, rounding
+/// up so the escrow never falls short of the quote. Without an oracle the two
+/// are treated as equal.
+pub fn convert(
+    querier: &QuerierWrapper,
+    config: &Config,
+    coin: &CoinPayment,
+    price: Uint128,
+) -> Result<Uint128, MarketplaceError> {
+    let oracle = match &config.oracle {
+        Some(oracle) => oracle,
+        None => return Ok(price),
+    };
+
+    let res: PriceResponse = querier.query_wasm_smart(
+        oracle,
+        &OracleQueryMsg::Price {
+            denom: denom(config, coin),
+        },
+    )?;
+    if res.price.is_zero() {
+        return Err(MarketplaceError::InvalidOraclePrice);
+    }
+    price
+        .checked_div_ceil(res.price)
+        .map_err(|_| MarketplaceError::InvalidOraclePrice)
+}
@@ -1,4 +1,4 @@
-use cosmwasm_std::{Addr, Uint128};
+use cosmwasm_std::{Addr, Empty, HexBinary, Uint128};
 use cw_storage_plus::{Item, Map};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -14,10 +14,39 @@ pub enum AccountType {
 pub enum RequestLifecycle {
     Pending,
     AcceptedBySeller,
+    // The buyer may still switch offers until the lock window has passed.
     AcceptedByBuyer,
+    // An accepted request whose lock window has passed, awaiting payment. No
+    // transaction marks that moment, so it is stored as `AcceptedByBuyer` and
+    // derived with `Request::lifecycle_at`.
     RequestLocked,
+    PartiallyPaid,
     Paid,
     Completed,
+    Cancelled,
+    Defaulted,
+}
+
+/// Fulfilment steps after payment, in the order they must be posted.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, PartialOrd, JsonSchema)]
+pub enum DeliveryStage {
+    Shipped,
+    OutForDelivery,
+    Delivered,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub enum RevisionStatus {
+    Proposed,
+    Accepted,
+    Rejected,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub enum AcceptStrategy {
+    LowestPrice,
+    /// Most completed sales, then lowest price.
+    HighestRatedSeller,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -26,6 +55,31 @@ pub enum CoinPayment {
     USDT,
 }
 // Structs
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Config {
+    pub admin: Addr,
+    pub usdt_addr: Addr,
+    pub time_to_lock: u64,
+    pub min_time_to_lock: u64,
+    pub max_time_to_lock: u64,
+    /// Converts prices into payment denoms; prices are paid 1:1 when unset.
+    pub oracle: Option<Addr>,
+    /// Bonded `COIN_DENOM` a seller needs to make offers, and what a slash takes.
+    pub min_stake: Uint128,
+    /// Seconds unbonded stake stays slashable before it can be withdrawn.
+    pub unbonding_period: u64,
+    /// Requests a buyer may have open at once, 0 for no limit.
+    pub max_open_requests: u32,
+    /// Offers one seller may make on a request over time, 0 for no limit.
+    pub max_offers_per_request: u32,
+    /// Distinct sellers that may bid on a request, 0 for no limit.
+    pub max_sellers_per_request: u32,
+    /// `COIN_DENOM` attached to every new request, refunded on completion.
+    pub creation_fee: Uint128,
+    /// Seconds after a `Delivered` milestone until anyone may release the escrow.
+    pub auto_release_after: u64,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Location {
     pub latitude: i128,
@@ -71,6 +125,173 @@ pub struct Request {
     pub updated_at: u64,
     pub paid: bool,
     pub accepted_offer_id: u64,
+    pub expires_at: Option<u64>,
+    pub time_to_lock: u64,
+    pub line_items: Vec<LineItem>,
+    pub budget: Option<Budget>,
+    pub auction: Option<SealedAuction>,
+    pub auto_accept: Option<AutoAccept>,
+    /// Creation fee held until the request closes.
+    pub creation_fee: Uint128,
+    /// Milestones posted by the seller once paid, oldest first.
+    pub delivery: Vec<DeliveryMilestone>,
+    /// Set by a moderator to drop the request from list queries.
+    pub hidden: bool,
+}
+
+impl Request {
+    /// A request only expires while it is still open for offers; once the buyer
+    /// has accepted an offer the deadline no longer applies.
+    pub fn is_expired(&self, now: u64) -> bool {
+        let open = matches!(
+            self.lifecycle,
+            RequestLifecycle::Pending | RequestLifecycle::AcceptedBySeller
+        );
+        open && self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+
+    /// When the escrow may be released without the buyer, once delivered.
+    pub fn auto_release_at(&self, auto_release_after: u64) -> Option<u64> {
+        self.delivery
+            .iter()
+            .find(|milestone| milestone.stage == DeliveryStage::Delivered)
+            .map(|milestone| milestone.at + auto_release_after)
+    }
+
+    /// The lifecycle as of `now`, with `RequestLocked` in place of
+    /// `AcceptedByBuyer` once the lock window has passed.
+    pub fn lifecycle_at(&self, now: u64) -> RequestLifecycle {
+        match self.lifecycle {
+            RequestLifecycle::AcceptedByBuyer if now >= self.updated_at + self.time_to_lock => {
+                RequestLifecycle::RequestLocked
+            }
+            ref lifecycle => lifecycle.clone(),
+        }
+    }
+
+    /// End of the current cooling-off window: after acceptance the buyer may
+    /// still switch offers, after payment the buyer may not yet complete.
+    pub fn locked_until(&self) -> Option<u64> {
+        match self.lifecycle {
+            RequestLifecycle::AcceptedByBuyer
+            | RequestLifecycle::RequestLocked
+            | RequestLifecycle::Paid => Some(self.updated_at + self.time_to_lock),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DeliveryMilestone {
+    pub stage: DeliveryStage,
+    /// Carrier tracking number or similar, if any.
+    pub tracking_ref: Option<String>,
+    pub at: u64,
+}
+
+/// Lets anyone accept the best offer on the buyer's behalf once `deadline`
+/// has passed, unless the buyer has accepted one already.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AutoAccept {
+    pub deadline: u64,
+    pub strategy: AcceptStrategy,
+}
+
+/// Sealed-bid reverse auction: sellers commit to a hidden price until
+/// `bidding_ends_at` and reveal it until `reveal_ends_at`. Then either anyone
+/// settles it on the lowest revealed price (`auto_select`) or the buyer picks
+/// among the revealed offers.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SealedAuction {
+    pub bidding_ends_at: u64,
+    pub reveal_ends_at: u64,
+    pub auto_select: bool,
+}
+
+/// The most a buyer is willing to pay: `max_price` in `PRICE_UNIT`, or in
+/// `coin` when set (offers are then converted through the oracle).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Budget {
+    pub max_price: u128,
+    pub coin: Option<CoinPayment>,
+}
+
+/// One entry of a shopping list, e.g. 5 kg of rice.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LineItem {
+    pub name: String,
+    pub quantity: u64,
+    pub unit: String,
+}
+
+/// A seller's quote for the request line at index `line`. `quantity` may be
+/// lower than requested when the seller can only partially fulfil it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LineQuote {
+    pub line: u32,
+    pub quantity: u64,
+    pub unit_price: u128,
+}
+
+impl LineQuote {
+    pub fn total(&self) -> u128 {
+        u128::from(self.quantity) * self.unit_price
+    }
+}
+
+/// A single installment, due `due_after` seconds after the deposit was paid.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Installment {
+    pub amount: u128,
+    pub due_after: u64,
+}
+
+/// Emergency switches set by the admin. `all` stops everything but stake
+/// withdrawals, backing out of unpaid requests and admin or moderator actions.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct PauseFlags {
+    pub all: bool,
+    /// Paying for requests, bonding stake and releasing escrow to sellers,
+    /// including forfeited deposits.
+    pub payments: bool,
+    /// Creating requests and creating, changing or negotiating offers.
+    pub listings: bool,
+}
+
+/// Native collateral bonded by a seller. Unbonding stake can still be slashed
+/// until `unbonding_ends_at`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct SellerStake {
+    pub bonded: Uint128,
+    pub unbonding: Uint128,
+    pub unbonding_ends_at: Option<u64>,
+}
+
+impl SellerStake {
+    /// Takes up to `amount` out of the stake, bonded funds first.
+    pub fn slash(&mut self, amount: Uint128) -> Uint128 {
+        let from_bonded = amount.min(self.bonded);
+        let from_unbonding = (amount - from_bonded).min(self.unbonding);
+        self.bonded -= from_bonded;
+        self.unbonding -= from_unbonding;
+        from_bonded + from_unbonding
+    }
+}
+
+/// One round of price negotiation on an offer, proposed by the buyer or the seller.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OfferRevision {
+    pub price: u128,
+    pub proposed_by: AccountType,
+    pub status: RevisionStatus,
+    pub created_at: u64,
+}
+
+/// Deposit plus installments; together they must add up to the offer price.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PaymentSchedule {
+    pub deposit: u128,
+    pub installments: Vec<Installment>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -82,9 +303,50 @@ pub struct Offer {
     pub store_name: String,
     pub seller_id: u64,
     pub is_accepted: bool,
+    pub is_cancelled: bool,
     pub created_at: u64,
     pub updated_at: u64,
     pub authority: Addr,
+    pub expires_at: Option<u64>,
+    pub payment_schedule: Option<PaymentSchedule>,
+    pub line_quotes: Vec<LineQuote>,
+    /// Lines the buyer picked when accepting; empty means the whole offer.
+    pub accepted_lines: Vec<u32>,
+    /// Hash of a sealed bid, cleared once the price is revealed.
+    pub commitment: Option<HexBinary>,
+    /// Counter-offer rounds and seller price updates, oldest first. The first
+    /// entry is the seller's original quote; `price` always holds the latest
+    /// agreed price.
+    pub revisions: Vec<OfferRevision>,
+    /// Set by a moderator to drop the offer from list queries; hidden offers
+    /// cannot be accepted.
+    pub hidden: bool,
+}
+
+impl Offer {
+    /// An accepted offer never expires, otherwise the quote lapses at `expires_at`.
+    pub fn is_expired(&self, now: u64) -> bool {
+        !self.is_accepted && self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+
+    /// The counter-offer still awaiting an answer, if any.
+    pub fn pending_revision(&self) -> Option<&OfferRevision> {
+        self.revisions
+            .last()
+            .filter(|revision| revision.status == RevisionStatus::Proposed)
+    }
+
+    /// What the buyer pays: the total of the accepted lines, or the full price.
+    pub fn accepted_price(&self) -> u128 {
+        if self.accepted_lines.is_empty() {
+            return self.price;
+        }
+        self.line_quotes
+            .iter()
+            .filter(|quote| self.accepted_lines.contains(&quote.line))
+            .map(LineQuote::total)
+            .sum()
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -97,9 +359,20 @@ pub struct PaymentInfo {
     pub coin: CoinPayment,
     pub created_at: u64,
     pub updated_at: u64,
+    pub installments_paid: u32,
+    pub next_due_at: Option<u64>,
+    /// Portion of the offer price covered so far, in `PRICE_UNIT`.
+    pub price_paid: Uint128,
+    /// Escrowed by the first payment: the deposit on installment plans.
+    pub deposit: Uint128,
+    /// Buyer's commitment to the secret handed over on delivery, see
+    /// `helpers::delivery_code`.
+    pub delivery_code: Option<HexBinary>,
 }
 
 // State
+pub const CONFIG: Item<Config> = Item::new("config");
+pub const PAUSED: Item<PauseFlags> = Item::new("paused");
 pub const USERS: Map<&[u8], User> = Map::new("users");
 pub const USERS_BY_ID: Map<u64, User> = Map::new("users_by_id");
 pub const REQUESTS: Map<u64, Request> = Map::new("requests");
@@ -112,6 +385,24 @@ pub const OFFER_COUNT: Item<u64> = Item::new("offer_count");
 pub const USER_COUNT: Item<u64> = Item::new("user_count");
 pub const STORE_COUNT: Item<u64> = Item::new("store_count");
 pub const PAYMENT_INFO: Map<u64, PaymentInfo> = Map::new("payment_info");
-pub const TIME_TO_LOCK: u64 = 900; // 15 minutes
+// Completed sales per seller id, the basis of `AcceptStrategy::HighestRatedSeller`.
+pub const SELLER_SALES: Map<u64, u64> = Map::new("seller_sales");
+pub const SELLER_STAKES: Map<u64, SellerStake> = Map::new("seller_stakes");
+// Paid or partially paid requests per seller id; `min_stake` stays bonded while any are open.
+pub const OPEN_ESCROWS: Map<u64, u32> = Map::new("open_escrows");
+// Requests per buyer id that are neither closed nor deleted, expired ones included.
+pub const OPEN_REQUESTS: Map<u64, u32> = Map::new("open_requests");
+// Managed by the admin, who can always moderate as well
+pub const MODERATORS: Map<&Addr, Empty> = Map::new("moderators");
+// User id => time of suspension
+pub const SUSPENDED_USERS: Map<u64, u64> = Map::new("suspended_users");
+pub const TIME_TO_LOCK: u64 = 900; // 15 minutes, default lock window
+pub const MIN_TIME_TO_LOCK: u64 = 60; // 1 minute
+pub const MAX_TIME_TO_LOCK: u64 = 604_800; // 7 days
+pub const UNBONDING_PERIOD: u64 = 604_800; // 7 days, default for withdrawing stake
+pub const AUTO_RELEASE_AFTER: u64 = 259_200; // 3 days after delivery
 pub const USDT_ADDR: &str = "terra1x46rqay4d3cssq8gxxvqz8xt6nwlz4td20k38v";
 pub const COIN_DENOM: &str = "uosmo";
+// Offer prices, deposits and installments are quoted in micro-USD.
+pub const PRICE_UNIT: &str = "USD";
+pub const PRICE_DECIMALS: u32 = 6;
@@ -0,0 +1,304 @@
+use crate::error::MarketplaceError;
+use crate::state::{LineItem, LineQuote, PaymentSchedule};
+
+pub const MAX_USERNAME_LEN: usize = 32;
+pub const MAX_NAME_LEN: usize = 64;
+pub const MAX_DESCRIPTION_LEN: usize = 1024;
+pub const MAX_IMAGES: usize = 5;
+pub const MAX_IMAGE_URL_LEN: usize = 256;
+pub const ALLOWED_IMAGE_SCHEMES: [&str; 2] = ["https://", "ipfs://"];
+// E.164: an optional leading `+` followed by up to 15 digits.
+pub const MIN_PHONE_DIGITS: usize = 7;
+pub const MAX_PHONE_DIGITS: usize = 15;
+pub const MAX_INSTALLMENTS: usize = 12;
+pub const MAX_LINE_ITEMS: usize = 50;
+pub const MAX_UNIT_LEN: usize = 16;
+pub const MAX_TRACKING_REF_LEN: usize = 64;
+
+/// Rejects empty (or whitespace only) and overlong text fields.
+pub fn validate_text(
+    field: &'static str,
+    value: &str,
+    max_len: usize,
+) -> Result<(), MarketplaceError> {
+    if value.trim().is_empty() {
+        return Err(MarketplaceError::EmptyField { field });
+    }
+    if value.len() > max_len {
+        return Err(MarketplaceError::FieldTooLong { field, max_len });
+    }
+    Ok(())
+}
+
+pub fn validate_phone(phone: &str) -> Result<(), MarketplaceError> {
+    let digits = phone.strip_prefix('+').unwrap_or(phone);
+    if digits.len() < MIN_PHONE_DIGITS
+        || digits.len() > MAX_PHONE_DIGITS
+        || !digits.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err(MarketplaceError::InvalidPhone);
+    }
+    Ok(())
+}
+
+pub fn validate_images(images: &[String]) -> Result<(), MarketplaceError> {
+    if images.len() > MAX_IMAGES {
+        return Err(MarketplaceError::TooManyImages {
+            max_images: MAX_IMAGES,
+        });
+    }
+    for image in images {
+        let scheme = ALLOWED_IMAGE_SCHEMES
+            .iter()
+            .find(|scheme| image.starts_with(*scheme));
+        match scheme {
+            Some(scheme) if image.len() > scheme.len() && image.len() <= MAX_IMAGE_URL_LEN => {}
+            _ => return Err(MarketplaceError::InvalidImageUrl),
+        }
+    }
+    Ok(())
+}
+
+pub fn validate_price(price: u128) -> Result<(), MarketplaceError> {
+    if price == 0 {
+        return Err(MarketplaceError::ZeroPrice);
+    }
+    Ok(())
+}
+
+pub fn validate_line_items(items: &[LineItem]) -> Result<(), MarketplaceError> {
+    if items.len() > MAX_LINE_ITEMS {
+        return Err(MarketplaceError::TooManyLineItems {
+            max_line_items: MAX_LINE_ITEMS,
+        });
+    }
+    for item in items {
+        validate_text("line_item.name", &item.name, MAX_NAME_LEN)?;
+        validate_text("line_item.unit", &item.unit, MAX_UNIT_LEN)?;
+        if item.quantity == 0 {
+            return Err(MarketplaceError::ZeroQuantity);
+        }
+    }
+    Ok(())
+}
+
+/// Checks quotes against the requested lines and returns the quoted total.
+/// Lines may be skipped or quoted for less than the requested quantity, but
+/// must be listed in increasing order with a non-zero quantity and unit price.
+pub fn validate_line_quotes(
+    items: &[LineItem],
+    quotes: &[LineQuote],
+) -> Result<u128, MarketplaceError> {
+    let mut total: u128 = 0;
+    let mut previous: Option<u32> = None;
+    for quote in quotes {
+        let invalid = MarketplaceError::InvalidLineQuote { line: quote.line };
+        let requested = match items.get(quote.line as usize) {
+            Some(item) => item.quantity,
+            None => return Err(invalid),
+        };
+        if previous.is_some_and(|previous| quote.line <= previous)
+            || quote.quantity == 0
+            || quote.quantity > requested
+            || quote.unit_price == 0
+        {
+            return Err(invalid);
+        }
+        previous = Some(quote.line);
+        total = u128::from(quote.quantity)
+            .checked_mul(quote.unit_price)
+            .and_then(|line_total| total.checked_add(line_total))
+            .ok_or(invalid)?;
+    }
+    Ok(total)
+}
+
+/// A schedule needs a non-zero deposit, between one and `MAX_INSTALLMENTS`
+/// non-zero installments with strictly increasing due dates, and must add up
+/// to exactly `price`.
+pub fn validate_payment_schedule(
+    price: u128,
+    schedule: &PaymentSchedule,
+) -> Result<(), MarketplaceError> {
+    let installments = &schedule.installments;
+    if schedule.deposit == 0 || installments.is_empty() || installments.len() > MAX_INSTALLMENTS {
+        return Err(MarketplaceError::InvalidPaymentSchedule);
+    }
+
+    let mut total = schedule.deposit;
+    let mut last_due_after = 0;
+    for installment in installments {
+        if installment.amount == 0 || installment.due_after <= last_due_after {
+            return Err(MarketplaceError::InvalidPaymentSchedule);
+        }
+        last_due_after = installment.due_after;
+        total = total
+            .checked_add(installment.amount)
+            .ok_or(MarketplaceError::InvalidPaymentSchedule)?;
+    }
+
+    if total != price {
+        return Err(MarketplaceError::InvalidPaymentSchedule);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Installment;
+
+    fn item(name: &str, quantity: u64, unit: &str) -> LineItem {
+        LineItem {
+            name: name.to_string(),
+            quantity,
+            unit: unit.to_string(),
+        }
+    }
+
+    #[test]
+    fn text() {
+        let too_long = "a".repeat(MAX_NAME_LEN + 1);
+        let cases: [(&str, bool); 5] = [
+            ("Rice", true),
+            ("", false),
+            ("   ", false),
+            (&too_long, false),
+            (&too_long[1..], true),
+        ];
+        for (value, ok) in cases {
+            let res = validate_text("name", value, MAX_NAME_LEN);
+            assert_eq!(res.is_ok(), ok, "{:?}", value);
+        }
+        assert!(matches!(
+            validate_text("name", "", MAX_NAME_LEN),
+            Err(MarketplaceError::EmptyField { field: "name" })
+        ));
+        assert!(matches!(
+            validate_text("name", &too_long, MAX_NAME_LEN),
+            Err(MarketplaceError::FieldTooLong {
+                field: "name",
+                max_len: MAX_NAME_LEN
+            })
+        ));
+    }
+
+    #[test]
+    fn phone() {
+        let cases = [
+            ("+2348012345678", true),
+            ("08012345678", true),
+            ("1234567", true),
+            ("123456", false),
+            ("+1234567890123456", false),
+            ("+234 801 234 5678", false),
+            ("+", false),
+            ("", false),
+            ("phone", false),
+        ];
+        for (phone, ok) in cases {
+            assert_eq!(validate_phone(phone).is_ok(), ok, "{:?}", phone);
+        }
+    }
+
+    #[test]
+    fn images() {
+        let long_url = format!("https://{}", "a".repeat(MAX_IMAGE_URL_LEN));
+        let cases: Vec<(Vec<String>, bool)> = vec![
+            (vec![], true),
+            (vec!["https://example.com/a.png".to_string()], true),
+            (vec!["ipfs://bafybeigdyrzt".to_string()], true),
+            (vec!["http://example.com/a.png".to_string()], false),
+            (vec!["https://".to_string()], false),
+            (vec!["data:image/png;base64,AAAA".to_string()], false),
+            (vec![long_url], false),
+            (vec!["ipfs://a".to_string(); MAX_IMAGES], true),
+            (vec!["ipfs://a".to_string(); MAX_IMAGES + 1], false),
+        ];
+        for (images, ok) in cases {
+            assert_eq!(validate_images(&images).is_ok(), ok, "{:?}", images);
+        }
+    }
+
+    #[test]
+    fn price() {
+        assert!(matches!(
+            validate_price(0),
+            Err(MarketplaceError::ZeroPrice)
+        ));
+        assert!(validate_price(1).is_ok());
+    }
+
+    #[test]
+    fn payment_schedule() {
+        let schedule = |deposit: u128, installments: &[(u128, u64)]| PaymentSchedule {
+            deposit,
+            installments: installments
+                .iter()
+                .map(|&(amount, due_after)| Installment { amount, due_after })
+                .collect(),
+        };
+        let too_many: Vec<(u128, u64)> =
+            (1..=MAX_INSTALLMENTS as u64 + 1).map(|i| (1, i)).collect();
+        let cases = [
+            (schedule(40, &[(30, 100), (30, 200)]), true),
+            (schedule(99, &[(1, 1)]), true),
+            (schedule(0, &[(100, 100)]), false),
+            (schedule(100, &[]), false),
+            (schedule(40, &[(30, 100), (0, 200), (30, 300)]), false),
+            (schedule(40, &[(30, 200), (30, 100)]), false),
+            (schedule(40, &[(30, 100), (30, 100)]), false),
+            (schedule(40, &[(30, 100)]), false),
+            (schedule(40, &[(30, 100), (40, 200)]), false),
+            (schedule(100 - too_many.len() as u128, &too_many), false),
+            (schedule(u128::MAX, &[(1, 1)]), false),
+        ];
+        for (schedule, ok) in cases {
+            let res = validate_payment_schedule(100, &schedule);
+            assert_eq!(res.is_ok(), ok, "{:?}", schedule);
+        }
+    }
+
+    #[test]
+    fn line_items() {
+        let too_many = vec![item("Rice", 1, "kg"); MAX_LINE_ITEMS + 1];
+        let cases = [
+            (vec![], true),
+            (vec![item("Rice", 5, "kg"), item("Eggs", 12, "pcs")], true),
+            (vec![item("Rice", 0, "kg")], false),
+            (vec![item("", 1, "kg")], false),
+            (vec![item("Rice", 1, "")], false),
+            (vec![item("Rice", 1, &"k".repeat(MAX_UNIT_LEN + 1))], false),
+            (too_many, false),
+        ];
+        for (items, ok) in cases {
+            assert_eq!(validate_line_items(&items).is_ok(), ok, "{:?}", items);
+        }
+    }
+
+    #[test]
+    fn line_quotes() {
+        let items = [item("Rice", 5, "kg"), item("Eggs", 12, "pcs")];
+        let quote = |line: u32, quantity: u64, unit_price: u128| LineQuote {
+            line,
+            quantity,
+            unit_price,
+        };
+        let cases = [
+            (vec![quote(0, 5, 10), quote(1, 12, 2)], Some(74)),
+            (vec![quote(1, 6, 2)], Some(12)),
+            (vec![], Some(0)),
+            (vec![quote(0, 6, 10)], None),
+            (vec![quote(0, 0, 10)], None),
+            (vec![quote(0, 5, 0)], None),
+            (vec![quote(2, 1, 10)], None),
+            (vec![quote(1, 1, 2), quote(0, 1, 10)], None),
+            (vec![quote(0, 1, 10), quote(0, 1, 10)], None),
+            (vec![quote(0, 5, u128::MAX)], None),
+        ];
+        for (quotes, total) in cases {
+            let res = validate_line_quotes(&items, &quotes);
+            assert_eq!(res.ok(), total, "{:?}", quotes);
+        }
+    }
+}